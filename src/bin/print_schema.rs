@@ -6,37 +6,80 @@ async fn main() -> anyhow::Result<()> {
     pub struct Arguments {
         /// database connection string for PostgreSQL
         #[argh(option, short = 'd')]
-        db: String,
+        db: Option<String>,
+
+        /// SQL DDL file to read instead of connecting to a database, such as `pg_dump --schema-only` output
+        #[argh(option)]
+        ddl: Option<std::path::PathBuf>,
 
         /// where to store generated schema file
         #[argh(option, short = 'o')]
         out: Option<std::path::PathBuf>,
 
-        /// specific database schema to use
+        /// database schema to use, may be given multiple times (default: all non-system schemas)
         #[argh(option, short = 's')]
-        schema: Option<String>,
+        schema: Vec<String>,
+
+        /// TOML generator config, with `pg::Type` overrides for non-builtin types
+        #[argh(option, short = 'c')]
+        config: Option<std::path::PathBuf>,
+
+        /// output format: rust (default), json or markdown
+        #[argh(option, short = 'f', default = "String::from(\"rust\")")]
+        format: String,
     }
 
     let args: Arguments = argh::from_env();
 
-    let (client, connection) = tokio_postgres::connect(&args.db, tokio_postgres::NoTls).await?;
+    use thorn::generate::{Config, Emitter, JsonEmitter, MarkdownEmitter, RustEmitter};
+
+    let config = match args.config {
+        Some(ref path) => Config::from_toml(&std::fs::read_to_string(path)?)?,
+        None => Config::default(),
+    };
+
+    let emitter: Box<dyn Emitter> = match &*args.format {
+        "rust" => Box::new(RustEmitter::new(config)),
+        "json" => Box::new(JsonEmitter),
+        "markdown" | "md" => Box::new(MarkdownEmitter),
+        format => anyhow::bail!("unknown format \"{format}\", expected rust, json or markdown"),
+    };
+
+    let model = match (&args.db, &args.ddl) {
+        (None, Some(ddl)) => {
+            let mut model = thorn::generate::parse_ddl(&std::fs::read_to_string(ddl)?)?;
+
+            if !args.schema.is_empty() {
+                model.schemas.retain(|s| args.schema.contains(&s.name));
+            }
+
+            model
+        }
+        (Some(db), None) => {
+            let (client, connection) = tokio_postgres::connect(db, tokio_postgres::NoTls).await?;
+
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("connection error: {}", e);
+                }
+            });
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
+            thorn::generate::introspect(&client, &args.schema).await?
         }
-    });
+        _ => anyhow::bail!("exactly one of --db or --ddl is required"),
+    };
 
-    let schema = thorn::generate::generate(&client, args.schema.clone()).await?;
+    let schema = emitter.emit(&model)?;
 
     if let Some(out) = args.out {
         use std::io::Write;
 
         let mut file = std::fs::OpenOptions::new().create(true).truncate(true).write(true).open(out)?;
 
-        match args.schema {
-            Some(schema) => write!(file, "//! Autogenerated Schema for \"{schema}\"\n\n")?,
-            None => write!(file, "//! Autogenerated Schema")?,
+        match args.schema[..] {
+            _ if args.format != "rust" => {}
+            [] => write!(file, "//! Autogenerated Schema\n\n")?,
+            ref schemas => write!(file, "//! Autogenerated Schema for \"{}\"\n\n", schemas.join("\", \""))?,
         }
 
         file.write_all(schema.as_bytes())?;
@@ -0,0 +1,379 @@
+//! Typed query builder for queries whose structure is chosen at runtime, such as list endpoints
+//! with user-selected filters, sorting and columns.
+//!
+//! ```ignore
+//! use thorn::builder::{ColumnExt, Order, Select};
+//!
+//! let mut select = Select::from::<Users>().cols(&[Users::Id, Users::UserName]);
+//!
+//! if let Some(ref name) = params.name {
+//!     select = select.filter(Users::UserName.eq(name));
+//! }
+//!
+//! let rows = client.query2(select.order_by(Users::Id, Order::Desc).limit(50).build()).await?;
+//! ```
+//!
+//! Builders produce the same [`Query`] as [`sql!`](crate::sql), so they can be used with
+//! [`ClientExt`](crate::extensions::ClientExt) methods.
+
+use std::panic::Location;
+
+use pgt::Row;
+
+use crate::macros::{Query, RowColumns, SourceLocation, SqlFormatError};
+use crate::table::TableExt;
+
+type Param<'a> = &'a (dyn pg::ToSql + Sync);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// A condition on the columns of table `T`, created with [`ColumnExt`] methods
+pub struct Filter<'a, T> {
+    node: Node<'a, T>,
+}
+
+enum Node<'a, T> {
+    Compare(T, &'static str, Param<'a>),
+    In(T, Vec<Param<'a>>),
+    Null(T, bool),
+    And(Vec<Filter<'a, T>>),
+    Or(Vec<Filter<'a, T>>),
+    Not(Box<Filter<'a, T>>),
+}
+
+impl<'a, T: TableExt> Filter<'a, T> {
+    pub fn and(self, other: Filter<'a, T>) -> Self {
+        match self.node {
+            Node::And(mut filters) => {
+                filters.push(other);
+                Filter {
+                    node: Node::And(filters),
+                }
+            }
+            _ => Filter {
+                node: Node::And(vec![self, other]),
+            },
+        }
+    }
+
+    pub fn or(self, other: Filter<'a, T>) -> Self {
+        match self.node {
+            Node::Or(mut filters) => {
+                filters.push(other);
+                Filter {
+                    node: Node::Or(filters),
+                }
+            }
+            _ => Filter {
+                node: Node::Or(vec![self, other]),
+            },
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Filter {
+            node: Node::Not(Box::new(self)),
+        }
+    }
+
+    /// All of the given filters, or `TRUE` if empty
+    pub fn all(filters: impl IntoIterator<Item = Filter<'a, T>>) -> Self {
+        Filter {
+            node: Node::And(filters.into_iter().collect()),
+        }
+    }
+
+    /// Any of the given filters, or `FALSE` if empty
+    pub fn any(filters: impl IntoIterator<Item = Filter<'a, T>>) -> Self {
+        Filter {
+            node: Node::Or(filters.into_iter().collect()),
+        }
+    }
+
+    fn write<E: RowColumns>(&self, q: &mut Query<'a, E>, nested: bool) -> Result<(), SqlFormatError> {
+        match self.node {
+            Node::Compare(col, op, value) => {
+                q.write_column(col, T::TYPENAME_SNAKE)?;
+                q.write_str(op);
+                q.__param::<true>(value, col.ty().pg, col.sensitive())?;
+            }
+            Node::In(_, ref values) if values.is_empty() => q.write_str("FALSE"),
+            Node::In(col, ref values) => {
+                q.write_column(col, T::TYPENAME_SNAKE)?;
+                q.write_str("IN (");
+
+                for (i, &value) in values.iter().enumerate() {
+                    if i > 0 {
+                        q.write_str(",");
+                    }
+
+                    q.__param::<true>(value, col.ty().pg, col.sensitive())?;
+                }
+
+                q.write_str(")");
+            }
+            Node::Null(col, is_null) => {
+                q.write_column(col, T::TYPENAME_SNAKE)?;
+                q.write_str(if is_null { "IS NULL" } else { "IS NOT NULL" });
+            }
+            Node::And(ref filters) => write_all(q, filters, "AND", "TRUE", nested)?,
+            Node::Or(ref filters) => write_all(q, filters, "OR", "FALSE", nested)?,
+            Node::Not(ref filter) => {
+                q.write_str("NOT");
+                filter.write(q, true)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_all<'a, T: TableExt, E: RowColumns>(
+    q: &mut Query<'a, E>,
+    filters: &[Filter<'a, T>],
+    op: &str,
+    empty: &str,
+    nested: bool,
+) -> Result<(), SqlFormatError> {
+    match filters {
+        [] => q.write_str(empty),
+        [filter] => filter.write(q, nested)?,
+        _ => {
+            if nested {
+                q.write_str("(");
+            }
+
+            for (i, filter) in filters.iter().enumerate() {
+                if i > 0 {
+                    q.write_str(op);
+                }
+
+                filter.write(q, true)?;
+            }
+
+            if nested {
+                q.write_str(")");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Filter methods for table columns
+pub trait ColumnExt: TableExt {
+    fn eq<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, "=", value)
+    }
+
+    fn ne<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, "!=", value)
+    }
+
+    fn lt<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, "<", value)
+    }
+
+    fn le<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, "<=", value)
+    }
+
+    fn gt<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, ">", value)
+    }
+
+    fn ge<'a, V: pg::ToSql + Sync>(self, value: &'a V) -> Filter<'a, Self> {
+        compare(self, ">=", value)
+    }
+
+    fn like<'a, V: pg::ToSql + Sync>(self, pattern: &'a V) -> Filter<'a, Self> {
+        compare(self, "LIKE", pattern)
+    }
+
+    fn ilike<'a, V: pg::ToSql + Sync>(self, pattern: &'a V) -> Filter<'a, Self> {
+        compare(self, "ILIKE", pattern)
+    }
+
+    /// `Col IN ($1, $2, ...)` with a parameter for each value, or `FALSE` if there are none
+    fn is_in<'a, V: pg::ToSql + Sync>(self, values: &'a [V]) -> Filter<'a, Self> {
+        Filter {
+            node: Node::In(self, values.iter().map(|v| v as Param<'a>).collect()),
+        }
+    }
+
+    fn is_null<'a>(self) -> Filter<'a, Self> {
+        Filter {
+            node: Node::Null(self, true),
+        }
+    }
+
+    fn is_not_null<'a>(self) -> Filter<'a, Self> {
+        Filter {
+            node: Node::Null(self, false),
+        }
+    }
+}
+
+impl<T: TableExt> ColumnExt for T {}
+
+fn compare<'a, T, V: pg::ToSql + Sync>(col: T, op: &'static str, value: &'a V) -> Filter<'a, T> {
+    Filter {
+        node: Node::Compare(col, op, value),
+    }
+}
+
+/// `SELECT` from a single table, see the [module documentation](self)
+pub struct Select<'a, T> {
+    cols: Vec<T>,
+    filters: Vec<Filter<'a, T>>,
+    order: Vec<(T, Order)>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl Select<'_, ()> {
+    /// Start a query on table `T`
+    pub fn from<'a, T: TableExt>() -> Select<'a, T> {
+        Select {
+            cols: Vec::new(),
+            filters: Vec::new(),
+            order: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+}
+
+impl<'a, T: TableExt> Select<'a, T> {
+    /// Add columns to the output, all columns are selected if none are given
+    pub fn cols(mut self, cols: &[T]) -> Self {
+        self.cols.extend_from_slice(cols);
+        self
+    }
+
+    /// Add a filter, multiple filters are combined with `AND`
+    pub fn filter(mut self, filter: Filter<'a, T>) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn order_by(mut self, col: T, order: Order) -> Self {
+        self.order.push((col, order));
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: i64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Build the query, with rows returned as [`Row`]
+    #[track_caller]
+    pub fn build(self) -> Result<Query<'a, Row>, SqlFormatError> {
+        self.build_as()
+    }
+
+    /// Build the query, with rows converted to `E`
+    #[track_caller]
+    pub fn build_as<E: RowColumns>(self) -> Result<Query<'a, E>, SqlFormatError> {
+        let caller = Location::caller();
+
+        let mut q = Query {
+            location: SourceLocation {
+                file: caller.file(),
+                line: caller.line(),
+                column: caller.column(),
+            },
+            ..Query::default()
+        };
+
+        q.write_str("SELECT");
+
+        let cols = if self.cols.is_empty() { T::COLUMNS } else { &self.cols[..] };
+
+        for (i, &col) in cols.iter().enumerate() {
+            if i > 0 {
+                q.write_str(",");
+            }
+
+            q.write_column(col, T::TYPENAME_SNAKE)?;
+        }
+
+        q.write_str("FROM");
+        q.write_table::<T>()?;
+
+        if !self.filters.is_empty() {
+            q.write_str("WHERE");
+            write_all(&mut q, &self.filters, "AND", "TRUE", false)?;
+        }
+
+        for (i, &(col, order)) in self.order.iter().enumerate() {
+            q.write_str(if i == 0 { "ORDER BY" } else { "," });
+            q.write_column(col, T::TYPENAME_SNAKE)?;
+            q.write_str(match order {
+                Order::Asc => "ASC",
+                Order::Desc => "DESC",
+            });
+        }
+
+        if let Some(limit) = self.limit {
+            q.write_str("LIMIT");
+            q.write_literal(limit)?;
+        }
+
+        if let Some(offset) = self.offset {
+            q.write_str("OFFSET");
+            q.write_literal(offset)?;
+        }
+
+        Ok(q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::TestTable;
+
+    #[test]
+    fn test_select_builder() {
+        let (id, names, pattern) = (1i64, vec!["a", "b"], "x%");
+
+        let query = Select::from::<TestTable>()
+            .cols(&[TestTable::Id])
+            .filter(TestTable::Id.gt(&id).or(TestTable::UserName.is_null()))
+            .filter(TestTable::UserName.is_in(&names).and(TestTable::UserName.like(&pattern).not()))
+            .order_by(TestTable::Id, Order::Desc)
+            .limit(10)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"SELECT "tt"."id" FROM "test_schema"."tt" WHERE ("tt"."id" > $1 OR "tt"."user_name" IS NULL) AND ("tt"."user_name" IN ($2, $3) AND NOT "tt"."user_name" LIKE $4) ORDER BY "tt"."id" DESC LIMIT 10"#
+        );
+
+        assert_eq!(
+            query.param_types(),
+            [pg::Type::INT8, pg::Type::TEXT, pg::Type::TEXT, pg::Type::TEXT]
+        );
+        assert_eq!(query.param_sensitivity(), [false, true, true, true]);
+        assert_eq!(query.location.file, file!());
+
+        let query = Select::from::<TestTable>().filter(TestTable::Id.is_in::<i64>(&[])).build().unwrap();
+        assert_eq!(
+            query.sql(),
+            r#"SELECT "tt"."id", "tt"."user_name" FROM "test_schema"."tt" WHERE FALSE"#
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Rendering queries with their parameters inlined as SQL literals, for logging and debugging.
+//!
+//! Each parameter is encoded with its [`ToSql`](pg::ToSql) implementation and decoded back into a literal
+//! cast to the parameter type, so the output can be pasted into `psql` and run as-is. Parameters
+//! of types that cannot be decoded are left as `$n` placeholders.
+
+use std::fmt::{self, Write};
+
+use pg::{private::BytesMut, IsNull, Kind, ToSql, Type};
+
+use crate::escape::{write_bytea, write_identifier, write_string};
+use crate::lexer;
+
+/// Which parameters to mask when rendering a query with
+/// [`Query::to_debug_sql_redacted`](crate::macros::Query::to_debug_sql_redacted).
+///
+/// Masked parameters are rendered as a typed `NULL` followed by a `/* redacted */` comment.
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    all: bool,
+    sensitive: bool,
+    types: Vec<Type>,
+}
+
+impl Redaction {
+    /// Mask nothing
+    pub fn none() -> Self {
+        Redaction::default()
+    }
+
+    /// Mask every parameter
+    pub fn all() -> Self {
+        Redaction {
+            all: true,
+            ..Redaction::default()
+        }
+    }
+
+    /// Mask parameters bound to columns marked `#[sensitive]` in [`tables!`](crate::tables)
+    pub fn sensitive() -> Self {
+        Redaction {
+            sensitive: true,
+            ..Redaction::default()
+        }
+    }
+
+    /// Also mask parameters of the given type, and arrays of it
+    pub fn with_type(mut self, ty: Type) -> Self {
+        self.types.push(ty);
+        self
+    }
+
+    /// Whether a parameter of the given type and sensitivity is masked
+    pub fn masks(&self, ty: &Type, sensitive: bool) -> bool {
+        if self.all || (self.sensitive && sensitive) || self.types.contains(ty) {
+            return true;
+        }
+
+        match ty.kind() {
+            Kind::Array(member) | Kind::Domain(member) => self.masks(member, false),
+            _ => false,
+        }
+    }
+}
+
+pub(crate) fn render(
+    sql: &str,
+    params: &[&(dyn ToSql + Sync + '_)],
+    tys: &[Type],
+    sensitive: &[bool],
+    redaction: &Redaction,
+) -> String {
+    let mut out = String::with_capacity(sql.len() + params.len() * 8);
+
+    let mut last = 0;
+
+    for (offset, placeholder) in lexer::tokens(sql) {
+        let Some(Ok(idx)) = placeholder.strip_prefix('$').map(str::parse::<usize>) else {
+            continue;
+        };
+
+        out.push_str(&sql[last..offset]);
+        last = offset + placeholder.len();
+
+        let idx = match idx {
+            1.. if idx <= params.len() && idx <= tys.len() => idx - 1,
+            _ => {
+                out.push_str(placeholder);
+                continue;
+            }
+        };
+
+        let ty = &tys[idx];
+
+        if redaction.masks(ty, sensitive.get(idx).copied().unwrap_or(false)) {
+            out.push_str("NULL::");
+            let _ = write_type(&mut out, ty);
+            out.push_str(" /* redacted */");
+            continue;
+        }
+
+        let mut value = String::new();
+
+        match write_param(&mut value, params[idx], ty) {
+            Ok(true) => out.push_str(&value),
+            Ok(false) => {
+                let _ = write!(out, "{placeholder} /* unsupported type {ty} */");
+            }
+            Err(e) => {
+                let _ = write!(out, "{placeholder} /* error: {} */", e.replace("*/", "* /"));
+            }
+        }
+    }
+
+    out.push_str(&sql[last..]);
+    out
+}
+
+/// Writes the SQL name of a type, suitable for a cast
+pub(crate) fn write_type(out: &mut String, ty: &Type) -> fmt::Result {
+    if let Kind::Array(member) = ty.kind() {
+        write_type(out, member)?;
+        return out.write_str("[]");
+    }
+
+    let simple = ty.name().bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+
+    match ty.schema() {
+        "" | "pg_catalog" | "public" if simple && *ty != Type::CHAR => out.write_str(ty.name()),
+        "" | "pg_catalog" | "public" => write_identifier(ty.name(), out),
+        schema => {
+            write_identifier(schema, &mut *out)?;
+            out.write_char('.')?;
+            write_identifier(ty.name(), out)
+        }
+    }
+}
+
+/// Writes the parameter as a literal cast to its type, returning false if the type is not supported.
+fn write_param(out: &mut String, value: &(dyn ToSql + Sync), ty: &Type) -> Result<bool, String> {
+    let mut buf = BytesMut::new();
+
+    let is_null = value.to_sql_checked(ty, &mut buf).map_err(|e| e.to_string())?;
+
+    if let IsNull::Yes = is_null {
+        out.push_str("NULL");
+    } else if !write_value(out, ty, &buf).map_err(|e| e.to_string())? {
+        return Ok(false);
+    }
+
+    out.push_str("::");
+    write_type(out, ty).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+#[derive(Debug, thiserror::Error)]
+enum DecodeError {
+    #[error("unexpected end of value")]
+    Eof,
+
+    #[error("invalid text encoding")]
+    Utf8,
+
+    #[error(transparent)]
+    Fmt(#[from] fmt::Error),
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < n {
+            return Err(DecodeError::Eof);
+        }
+
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn i16(&mut self) -> Result<i16, DecodeError> {
+        self.array().map(i16::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, DecodeError> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    fn i64(&mut self) -> Result<i64, DecodeError> {
+        self.array().map(i64::from_be_bytes)
+    }
+}
+
+fn text(buf: &[u8]) -> Result<&str, DecodeError> {
+    std::str::from_utf8(buf).map_err(|_| DecodeError::Utf8)
+}
+
+/// Writes a binary-encoded value as an SQL literal, without a cast. Returns false if the type is not supported.
+fn write_value(out: &mut String, ty: &Type, buf: &[u8]) -> Result<bool, DecodeError> {
+    let mut r = Reader(buf);
+
+    match ty.kind() {
+        Kind::Domain(base) => return write_value(out, base, buf),
+        Kind::Array(member) => return write_array(out, member, buf),
+        Kind::Enum(_) => {
+            write_string(text(buf)?, out)?;
+            return Ok(true);
+        }
+        _ => {}
+    }
+
+    match *ty {
+        Type::BOOL => out.push_str(if r.array::<1>()?[0] != 0 { "TRUE" } else { "FALSE" }),
+        Type::CHAR => write!(out, "{}", r.array::<1>()?[0] as i8)?,
+        Type::INT2 => write!(out, "{}", r.i16()?)?,
+        Type::INT4 => write!(out, "{}", r.i32()?)?,
+        Type::INT8 => write!(out, "{}", r.i64()?)?,
+        Type::OID => write!(out, "{}", r.i32()? as u32)?,
+        Type::FLOAT4 => write_float(out, f32::from_be_bytes(r.array()?) as f64)?,
+        Type::FLOAT8 => write_float(out, f64::from_be_bytes(r.array()?))?,
+        Type::NUMERIC => write_numeric(out, &mut r)?,
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::JSON | Type::XML => {
+            write_string(text(buf)?, out)?
+        }
+        // jsonb is prefixed with a version byte
+        Type::JSONB => write_string(text(buf.get(1..).ok_or(DecodeError::Eof)?)?, out)?,
+        Type::BYTEA => write_bytea(buf, out)?,
+        Type::UUID => {
+            let b = r.array::<16>()?;
+            out.push('\'');
+            for (i, byte) in b.iter().enumerate() {
+                if matches!(i, 4 | 6 | 8 | 10) {
+                    out.push('-');
+                }
+                write!(out, "{byte:02x}")?;
+            }
+            out.push('\'');
+        }
+        Type::DATE => match r.i32()? {
+            i32::MAX => out.push_str("'infinity'"),
+            i32::MIN => out.push_str("'-infinity'"),
+            days => {
+                out.push('\'');
+                let bc = write_date(out, days as i64)?;
+                out.push_str(if bc { " BC'" } else { "'" });
+            }
+        },
+        Type::TIME => {
+            out.push('\'');
+            write_time(out, r.i64()?)?;
+            out.push('\'');
+        }
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => match r.i64()? {
+            i64::MAX => out.push_str("'infinity'"),
+            i64::MIN => out.push_str("'-infinity'"),
+            micros => {
+                const DAY: i64 = 86_400_000_000;
+
+                out.push('\'');
+                let bc = write_date(out, micros.div_euclid(DAY))?;
+                out.push(' ');
+                write_time(out, micros.rem_euclid(DAY))?;
+
+                if *ty == Type::TIMESTAMPTZ {
+                    out.push_str("+00");
+                }
+
+                out.push_str(if bc { " BC'" } else { "'" });
+            }
+        },
+        Type::INTERVAL => {
+            let micros = r.i64()?;
+            let days = r.i32()?;
+            let months = r.i32()?;
+
+            write!(out, "'{months} months {days} days {micros} microseconds'")?;
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+fn write_float(out: &mut String, value: f64) -> fmt::Result {
+    match value {
+        _ if value.is_nan() => out.write_str("'NaN'"),
+        f64::INFINITY => out.write_str("'Infinity'"),
+        f64::NEG_INFINITY => out.write_str("'-Infinity'"),
+        _ => write!(out, "{value}"),
+    }
+}
+
+/// Writes a date given in days since 2000-01-01, returning true if the year is BC
+fn write_date(out: &mut String, days: i64) -> Result<bool, DecodeError> {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 10_957 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    // there is no year 0, it's 1 BC
+    let (year, bc) = if year <= 0 { (1 - year, true) } else { (year, false) };
+
+    write!(out, "{year:04}-{month:02}-{day:02}")?;
+
+    Ok(bc)
+}
+
+/// Writes a time of day given in microseconds since midnight
+fn write_time(out: &mut String, micros: i64) -> fmt::Result {
+    let (secs, frac) = (micros / 1_000_000, micros % 1_000_000);
+
+    write!(out, "{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)?;
+
+    if frac != 0 {
+        write!(out, ".{frac:06}")?;
+    }
+
+    Ok(())
+}
+
+fn write_numeric(out: &mut String, r: &mut Reader) -> Result<(), DecodeError> {
+    let ndigits = r.i16()? as u16 as usize;
+    let weight = r.i16()? as i32;
+    let sign = r.i16()? as u16;
+    let dscale = r.i16()? as u16 as usize;
+
+    let special = match sign {
+        0xC000 => "'NaN'",
+        0xD000 => "'Infinity'",
+        0xF000 => "'-Infinity'",
+        _ => "",
+    };
+
+    if !special.is_empty() {
+        out.push_str(special);
+        return Ok(());
+    }
+
+    if sign == 0x4000 {
+        out.push('-');
+    }
+
+    let digits = (0..ndigits).map(|_| r.i16()).collect::<Result<Vec<_>, _>>()?;
+
+    // digit groups are base 10000, with the first group at 10000^weight
+    let digit = |i: i32| if i < 0 { 0 } else { digits.get(i as usize).copied().unwrap_or(0) };
+
+    if weight < 0 {
+        out.push('0');
+    } else {
+        write!(out, "{}", digit(0))?;
+        for i in 1..=weight {
+            write!(out, "{:04}", digit(i))?;
+        }
+    }
+
+    if dscale > 0 {
+        let mut frac = String::with_capacity(dscale + 4);
+
+        let mut i = weight + 1;
+        while frac.len() < dscale {
+            write!(frac, "{:04}", digit(i))?;
+            i += 1;
+        }
+
+        frac.truncate(dscale);
+        out.push('.');
+        out.push_str(&frac);
+    }
+
+    Ok(())
+}
+
+fn write_array(out: &mut String, member: &Type, buf: &[u8]) -> Result<bool, DecodeError> {
+    let mut r = Reader(buf);
+
+    let ndim = r.i32()?.max(0) as usize;
+    let _has_nulls = r.i32()?;
+    let _oid = r.i32()?;
+
+    let mut dims = Vec::with_capacity(ndim);
+    for _ in 0..ndim {
+        dims.push(r.i32()?.max(0) as usize);
+        let _lower_bound = r.i32()?;
+    }
+
+    if ndim == 0 || dims.contains(&0) {
+        out.push_str("'{}'");
+        return Ok(true);
+    }
+
+    write_array_dim(out, member, &dims, &mut r)
+}
+
+fn write_array_dim(out: &mut String, member: &Type, dims: &[usize], r: &mut Reader) -> Result<bool, DecodeError> {
+    out.push_str("ARRAY[");
+
+    for i in 0..dims[0] {
+        if i > 0 {
+            out.push_str(", ");
+        }
+
+        if dims.len() > 1 {
+            // nested arrays are written without the ARRAY keyword
+            let start = out.len();
+            if !write_array_dim(out, member, &dims[1..], r)? {
+                return Ok(false);
+            }
+            out.replace_range(start..start + "ARRAY".len(), "");
+            continue;
+        }
+
+        match r.i32()? {
+            -1 => out.push_str("NULL"),
+            len => {
+                let value = r.take(len.max(0) as usize)?;
+                if !write_value(out, member, value)? {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    out.push(']');
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Redaction;
+    use crate::pg::{Kind, Type};
+    use crate::table::TestTable;
+
+    #[test]
+    fn test_debug_sql() {
+        let id = 1i64;
+        let name = "it's";
+        let ids = vec![1i64, 2];
+
+        let query = sql! {
+            SELECT "$1", TestTable.Id FROM TestTable
+            WHERE TestTable.Id = #{&id as TestTable::Id} AND TestTable.UserName = #{&name as TestTable::UserName}
+            AND TestTable.Id = ANY(#{&ids as Type::INT8_ARRAY})
+        }
+        .unwrap();
+
+        let sql = query.to_debug_sql();
+        assert!(sql.starts_with("SELECT '$1'"));
+        assert!(sql.contains("= 1::int8"));
+        assert!(sql.contains("= 'it''s'::text"));
+        assert!(sql.contains("ARRAY[1, 2]::int8[]"));
+
+        let sql = query.to_debug_sql_redacted(&Redaction::sensitive().with_type(Type::INT8));
+        assert!(sql.contains("= NULL::int8 /* redacted */"));
+        assert!(sql.contains("= NULL::text /* redacted */"));
+        assert!(sql.contains("NULL::int8[] /* redacted */"));
+    }
+
+    struct Label;
+
+    impl From<Label> for Type {
+        fn from(_: Label) -> Type {
+            Type::TEXT
+        }
+    }
+
+    #[test]
+    fn test_into_type_param() {
+        let name = "a";
+
+        let query = sql! {
+            SELECT TestTable.Id FROM TestTable WHERE TestTable.UserName = #{&name as Label}
+        }
+        .unwrap();
+
+        assert_eq!(query.param_types(), [Type::TEXT]);
+        assert_eq!(query.param_sensitivity(), [false]);
+        assert!(query.to_debug_sql_redacted(&Redaction::sensitive()).contains("= 'a'::text"));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let bytes = vec![0u8, 39, 255];
+
+        let query = sql! { SELECT #{&bytes as Type::BYTEA} }.unwrap();
+        assert_eq!(query.to_debug_sql(), r"SELECT E'\\x0027ff'::bytea");
+
+        let mut out = String::new();
+        let ty = Type::new(r#"my "type""#.into(), 0, Kind::Simple, "my schema".into());
+        super::write_type(&mut out, &ty).unwrap();
+        assert_eq!(out, r#""my schema"."my ""type""""#);
+
+        out.clear();
+        super::write_type(&mut out, &Type::CHAR_ARRAY).unwrap();
+        assert_eq!(out, r#""char"[]"#);
+    }
+}
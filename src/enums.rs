@@ -7,10 +7,15 @@ pub trait EnumType: Clone + Copy + Sized + 'static {
     const VARIANTS: &'static [Self];
 
     fn full_name() -> String {
-        match Self::SCHEMA {
-            Schema::None => format!("\"{}\"", Self::NAME.name()),
-            Schema::Named(name) => format!("\"{}\".\"{}\"", name, Self::NAME.name()),
+        let mut out = String::new();
+
+        if let Schema::Named(name) = Self::SCHEMA {
+            let _ = crate::escape::write_identifier(name, &mut out);
+            out.push('.');
         }
+
+        let _ = crate::escape::write_identifier(Self::NAME.name(), &mut out);
+        out
     }
 
     fn name(&self) -> &'static str;
@@ -0,0 +1,125 @@
+//! Escaping of string, bytea and identifier literals, shared with `thorn_macros`.
+//!
+//! `thorn_macros` keeps a copy of this file in `thorn_macros/src/escape.rs`, which must stay byte-identical.
+//!
+//! Strings are written as standard `'...'` literals with doubled quotes, unless they contain a backslash,
+//! in which case they are written as `E'...'` with escaped backslashes. This makes the output
+//! independent of the `standard_conforming_strings` setting.
+//!
+//! PostgreSQL text cannot contain NUL characters, so strings and identifiers with `\0` are rejected,
+//! as are empty identifiers.
+//!
+//! See <https://www.postgresql.org/docs/current/sql-syntax-lexical.html>
+
+use std::fmt::{self, Write};
+
+/// Write `string` as a quoted string constant, such as `'it''s'` or `E'C:\\path'`
+pub fn write_string(string: &str, mut w: impl Write) -> fmt::Result {
+    if string.contains('\0') {
+        return Err(fmt::Error);
+    }
+
+    let backslash = string.contains('\\');
+
+    w.write_str(if backslash { "E'" } else { "'" })?;
+
+    for (i, part) in string.split('\'').enumerate() {
+        if i > 0 {
+            w.write_str("''")?;
+        }
+
+        if backslash {
+            for (j, part) in part.split('\\').enumerate() {
+                if j > 0 {
+                    w.write_str("\\\\")?;
+                }
+
+                w.write_str(part)?;
+            }
+        } else {
+            w.write_str(part)?;
+        }
+    }
+
+    w.write_str("'")
+}
+
+/// Write `name` as a quoted identifier with doubled quotes, such as `"my ""table"""`
+///
+/// Identifiers cannot be empty.
+pub fn write_identifier(name: &str, mut w: impl Write) -> fmt::Result {
+    if name.is_empty() || name.contains('\0') {
+        return Err(fmt::Error);
+    }
+
+    w.write_str("\"")?;
+
+    for (i, part) in name.split('"').enumerate() {
+        if i > 0 {
+            w.write_str("\"\"")?;
+        }
+
+        w.write_str(part)?;
+    }
+
+    w.write_str("\"")
+}
+
+/// Write `bytes` as a `bytea` string constant in hex format, such as `E'\\x00ff'`
+///
+/// See <https://www.postgresql.org/docs/current/datatype-binary.html#DATATYPE-BINARY-BYTEA-HEX-FORMAT>
+pub fn write_bytea(bytes: &[u8], mut w: impl Write) -> fmt::Result {
+    w.write_str("E'\\\\x")?;
+
+    for byte in bytes {
+        write!(w, "{byte:02x}")?;
+    }
+
+    w.write_str("'")
+}
+
+/// Write `string` as a double-quoted element of an array literal, such as `"a \"b\""`,
+/// which must then itself be written as a string constant.
+///
+/// See <https://www.postgresql.org/docs/current/arrays.html#ARRAYS-IO>
+pub fn write_array_element(string: &str, mut w: impl Write) -> fmt::Result {
+    if string.contains('\0') {
+        return Err(fmt::Error);
+    }
+
+    w.write_str("\"")?;
+
+    for c in string.chars() {
+        if matches!(c, '"' | '\\') {
+            w.write_char('\\')?;
+        }
+
+        w.write_char(c)?;
+    }
+
+    w.write_str("\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escaped(f: impl FnOnce(&mut String) -> fmt::Result) -> String {
+        let mut buf = String::new();
+        f(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escaped(|w| write_string("it's \"quoted\"", w)), r#"'it''s "quoted"'"#);
+        assert_eq!(escaped(|w| write_string(r"C:\path's", w)), r"E'C:\\path''s'");
+        assert_eq!(escaped(|w| write_identifier(r#"my "table""#, w)), r#""my ""table""""#);
+        assert_eq!(escaped(|w| write_bytea(&[0, 10, 255], w)), r"E'\\x000aff'");
+        assert_eq!(escaped(|w| write_array_element(r#"a "b" \c"#, w)), r#""a \"b\" \\c""#);
+
+        assert!(write_string("nul\0", &mut String::new()).is_err());
+        assert!(write_identifier("nul\0", &mut String::new()).is_err());
+        assert!(write_identifier("", &mut String::new()).is_err());
+    }
+}
@@ -1,8 +1,29 @@
+use std::time::Duration;
+
 use futures_util::{Stream, StreamExt};
 use pg::ToSql;
-use pgt::{Client, Row, RowStream};
+use pgt::{
+    error::{ErrorPosition, SqlState},
+    Client, GenericClient, IsolationLevel, Row, RowStream, Statement, Transaction,
+};
+
+use crate::macros::{Query, RowColumns, SourceLocation, SqlFormatError};
+use crate::source_map::SqlSpan;
+
+mod cache;
+mod constraint;
+
+#[cfg(feature = "explain")]
+mod explain;
+
+pub use cache::{is_stale_statement_error, CacheKey, CacheStats, StatementCache};
+pub use constraint::ConstraintViolation;
 
-use crate::macros::{Query, RowColumns, SqlFormatError};
+#[cfg(feature = "explain")]
+pub use explain::{Explain, ExplainOptions, PlanNode};
+
+#[cfg(all(feature = "explain", feature = "registry"))]
+pub use explain::{check_plans, PlanReport, SeqScan};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -10,7 +31,91 @@ pub enum Error {
     SqlFormat(#[from] SqlFormatError),
 
     #[error("Postgres error: {0}")]
-    Postgres(#[from] pgt::Error),
+    Postgres(pgt::Error),
+
+    /// Postgres rejected the query text, with the location of the error in the query
+    #[error("{0}")]
+    Sql(Box<SqlError>),
+
+    #[error("Unique violation: {0}")]
+    UniqueViolation(Box<ConstraintViolation>),
+
+    #[error("Foreign key violation: {0}")]
+    ForeignKeyViolation(Box<ConstraintViolation>),
+
+    #[error("Not-null violation: {0}")]
+    NotNullViolation(Box<ConstraintViolation>),
+
+    #[error("Check violation: {0}")]
+    CheckViolation(Box<ConstraintViolation>),
+
+    #[error("Query at {0} returned no rows, expected exactly one")]
+    NoRows(SourceLocation),
+
+    #[error("Query at {0} returned more than one row")]
+    TooManyRows(SourceLocation),
+}
+
+/// Database error that points into the query text
+#[derive(Debug, thiserror::Error)]
+#[error("{}\n{span}", match source.as_db_error() { Some(db) => db.message(), None => "Postgres error" })]
+pub struct SqlError {
+    pub source: pgt::Error,
+    pub span: SqlSpan,
+}
+
+impl From<pgt::Error> for Error {
+    fn from(err: pgt::Error) -> Self {
+        let variant: fn(Box<ConstraintViolation>) -> Error = match err.code() {
+            Some(code) if *code == SqlState::UNIQUE_VIOLATION => Error::UniqueViolation,
+            Some(code) if *code == SqlState::FOREIGN_KEY_VIOLATION => Error::ForeignKeyViolation,
+            Some(code) if *code == SqlState::NOT_NULL_VIOLATION => Error::NotNullViolation,
+            Some(code) if *code == SqlState::CHECK_VIOLATION => Error::CheckViolation,
+            _ => return Error::Postgres(err),
+        };
+
+        match ConstraintViolation::new(err) {
+            Ok(violation) => variant(violation),
+            Err(err) => Error::Postgres(err),
+        }
+    }
+}
+
+impl Error {
+    /// The underlying database error, if any
+    pub fn as_postgres(&self) -> Option<&pgt::Error> {
+        match self {
+            Error::Postgres(e) => Some(e),
+            Error::Sql(e) => Some(&e.source),
+            Error::UniqueViolation(v)
+            | Error::ForeignKeyViolation(v)
+            | Error::NotNullViolation(v)
+            | Error::CheckViolation(v) => Some(&v.source),
+            _ => None,
+        }
+    }
+
+    /// The SQLSTATE code of the underlying database error, if any
+    pub fn code(&self) -> Option<&SqlState> {
+        self.as_postgres()?.code()
+    }
+
+    /// The constraint violation details, if any
+    pub fn violation(&self) -> Option<&ConstraintViolation> {
+        match self {
+            Error::UniqueViolation(v)
+            | Error::ForeignKeyViolation(v)
+            | Error::NotNullViolation(v)
+            | Error::CheckViolation(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns true for serialization failures and deadlocks, after which the
+    /// whole transaction can be safely retried.
+    pub fn is_transaction_conflict(&self) -> bool {
+        matches!(self.code(), Some(code) if *code == SqlState::T_R_SERIALIZATION_FAILURE || *code == SqlState::T_R_DEADLOCK_DETECTED)
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -32,31 +137,340 @@ pub trait ClientExt {
         }
         Ok(rows)
     }
+
+    /// Executes a statement, returning the number of rows modified.
+    async fn execute2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<u64, Error>;
+
+    /// Executes a query that must return exactly one row.
+    async fn query_one2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<E, Error> {
+        let location = location_of(&query);
+
+        match self.query_opt2(query).await? {
+            Some(row) => Ok(row),
+            None => Err(Error::NoRows(location)),
+        }
+    }
+
+    /// Executes a query that must return at most one row.
+    async fn query_opt2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<Option<E>, Error> {
+        let location = location_of(&query);
+
+        let mut stream = std::pin::pin!(self.query_stream2(query).await?);
+
+        let row = match stream.next().await {
+            Some(row) => row?,
+            None => return Ok(None),
+        };
+
+        if stream.next().await.is_some() {
+            return Err(Error::TooManyRows(location));
+        }
+
+        Ok(Some(row))
+    }
+
+    /// Runs the query under `EXPLAIN (FORMAT JSON, ...)` with the same parameters, returning the plan tree.
+    #[cfg(feature = "explain")]
+    async fn explain2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+        opts: ExplainOptions,
+    ) -> Result<Explain, Error> {
+        let query = explain::wrap(&query?, opts);
+        let location = query.location;
+
+        let row = self.query_one2(Ok(query)).await?;
+
+        explain::parse(&row, location)
+    }
+}
+
+fn location_of<E: RowColumns>(query: &Result<Query<'_, E>, SqlFormatError>) -> SourceLocation {
+    match query {
+        Ok(query) => query.location,
+        Err(_) => SourceLocation::default(),
+    }
 }
 
-impl ClientExt for Client {
+fn slice_iter<'a>(s: &'a [&'a (dyn ToSql + Sync)]) -> impl ExactSizeIterator<Item = &'a dyn ToSql> + 'a {
+    s.iter().map(|s| *s as _)
+}
+
+/// Attach the location of the error within the query, if Postgres reported one
+fn query_error<E: RowColumns>(err: pgt::Error, query: &Query<'_, E>) -> Error {
+    if let Some(&ErrorPosition::Original(position)) = err.as_db_error().and_then(|db| db.position()) {
+        let span = SqlSpan::from_position(query.sql(), query.source_map(), query.location, position as usize);
+
+        return Error::Sql(Box::new(SqlError { source: err, span }));
+    }
+
+    Error::from(err)
+}
+
+fn map_rows<E: RowColumns>(stream: RowStream) -> impl Stream<Item = Result<E, Error>> {
+    stream.map(|r| match r {
+        Ok(row) => Ok(E::from(row)),
+        Err(e) => Err(e.into()),
+    })
+}
+
+impl<C: GenericClient + Sync> ClientExt for C {
     async fn query_stream2<'a, E: RowColumns + Send + Sync + 'static>(
         &self,
         query: Result<Query<'a, E>, SqlFormatError>,
     ) -> Result<impl Stream<Item = Result<E, Error>>, Error> {
-        fn slice_iter<'a>(s: &'a [&'a (dyn ToSql + Sync)]) -> impl ExactSizeIterator<Item = &'a dyn ToSql> + 'a {
-            s.iter().map(|s| *s as _)
+        let query = query?;
+
+        let stmt = match self.prepare_typed(query.sql(), query.param_types()).await {
+            Ok(stmt) => stmt,
+            Err(e) => return Err(query_error(e, &query)),
+        };
+
+        let stream = match self.query_raw(&stmt, slice_iter(&query.params)).await {
+            Ok(stream) => stream,
+            Err(e) => return Err(query_error(e, &query)),
+        };
+
+        Ok(map_rows(stream))
+    }
+
+    async fn execute2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<u64, Error> {
+        let query = query?;
+
+        let stmt = match self.prepare_typed(query.sql(), query.param_types()).await {
+            Ok(stmt) => stmt,
+            Err(e) => return Err(query_error(e, &query)),
+        };
+
+        self.execute_raw(&stmt, slice_iter(&query.params)).await.map_err(|e| query_error(e, &query))
+    }
+}
+
+/// [`Client`] wrapper that reuses prepared statements through a [`StatementCache`]
+/// instead of preparing each query on every execution.
+pub struct CachedClient {
+    client: Client,
+    cache: StatementCache,
+}
+
+impl CachedClient {
+    pub fn new(client: Client, cache: StatementCache) -> Self {
+        CachedClient { client, cache }
+    }
+
+    #[inline]
+    pub fn cache(&self) -> &StatementCache {
+        &self.cache
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Client {
+        self.client
+    }
+
+    async fn prepare_cached(
+        &self,
+        key: Option<&CacheKey>,
+        q: &str,
+        tys: &[pg::Type],
+    ) -> Result<Statement, pgt::Error> {
+        if let Some(stmt) = key.and_then(|key| self.cache.get(key)) {
+            return Ok(stmt);
         }
 
-        let mut query = query?;
+        let stmt = self.client.prepare_typed(q, tys).await?;
+
+        if let Some(key) = key {
+            self.cache.insert(key.clone(), stmt.clone());
+        }
+
+        Ok(stmt)
+    }
+
+    /// If the schema changed underneath a cached statement, invalidate it and
+    /// return true to prepare it again, but only once.
+    fn should_retry(&self, key: Option<&CacheKey>, err: &pgt::Error, retried: &mut bool) -> bool {
+        self.cache.retry_stale(key, is_stale_statement_error(err), retried)
+    }
+}
+
+impl std::ops::Deref for CachedClient {
+    type Target = Client;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl ClientExt for CachedClient {
+    async fn query_stream2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<impl Stream<Item = Result<E, Error>>, Error> {
+        let query = query?;
+
+        let key = CacheKey::for_query(&query, self.cache.caches_dynamic());
+        let (q, tys) = (query.sql(), query.param_types());
+
+        let mut retried = false;
+
+        loop {
+            let stmt = match self.prepare_cached(key.as_ref(), q, tys).await {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(query_error(e, &query)),
+            };
 
-        let (q, tys) = match query.cached {
-            Some(cached) => (&cached.q, &cached.params),
-            None => (&query.q, &query.param_tys),
+            match self.client.query_raw(&stmt, slice_iter(&query.params)).await {
+                Ok(stream) => return Ok(map_rows(stream)),
+                Err(e) if self.should_retry(key.as_ref(), &e, &mut retried) => continue,
+                Err(e) => return Err(query_error(e, &query)),
+            }
+        }
+    }
+
+    async fn execute2<'a, E: RowColumns + Send + Sync + 'static>(
+        &self,
+        query: Result<Query<'a, E>, SqlFormatError>,
+    ) -> Result<u64, Error> {
+        let query = query?;
+
+        let key = CacheKey::for_query(&query, self.cache.caches_dynamic());
+        let (q, tys) = (query.sql(), query.param_types());
+
+        let mut retried = false;
+
+        loop {
+            let stmt = match self.prepare_cached(key.as_ref(), q, tys).await {
+                Ok(stmt) => stmt,
+                Err(e) => return Err(query_error(e, &query)),
+            };
+
+            match self.client.execute_raw(&stmt, slice_iter(&query.params)).await {
+                Ok(rows) => return Ok(rows),
+                Err(e) if self.should_retry(key.as_ref(), &e, &mut retried) => continue,
+                Err(e) => return Err(query_error(e, &query)),
+            }
+        }
+    }
+}
+
+/// Backoff policy for [`transaction_with_retry`]
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each subsequent retry
+    pub base_delay: Duration,
+    /// Upper bound on the delay between retries
+    pub max_delay: Duration,
+    /// Isolation level to run the transaction at, otherwise the session default
+    pub isolation_level: Option<IsolationLevel>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_secs(1),
+            isolation_level: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay.saturating_mul(1 << attempt.min(16)).min(self.max_delay)
+    }
+}
+
+/// Runs `f` inside a transaction and commits it, rerunning the whole transaction
+/// with backoff on serialization failures and deadlocks (SQLSTATE `40001`/`40P01`).
+///
+/// `f` may be called multiple times, so it should not have side effects outside of the transaction.
+///
+/// This takes a [`Client`] rather than any [`GenericClient`], because a transaction started inside
+/// another [`Transaction`] is only a savepoint, which can neither set its own isolation level
+/// nor recover from a serialization failure of the enclosing transaction.
+pub async fn transaction_with_retry<T, F>(client: &mut Client, policy: RetryPolicy, mut f: F) -> Result<T, Error>
+where
+    F: AsyncFnMut(&Transaction<'_>) -> Result<T, Error>,
+{
+    let mut attempt = 0;
+
+    loop {
+        let res: Result<T, Error> = async {
+            let mut builder = client.build_transaction();
+
+            if let Some(isolation) = policy.isolation_level {
+                builder = builder.isolation_level(isolation);
+            }
+
+            let tx = builder.start().await?;
+
+            let value = f(&tx).await?;
+            tx.commit().await?;
+
+            Ok(value)
+        }
+        .await;
+
+        attempt += 1;
+
+        match res {
+            Err(e) if attempt < policy.max_attempts && e.is_transaction_conflict() => {
+                tokio::time::sleep(policy.delay(attempt - 1)).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set `THORN_TEST_DB` to a connection string to run
+    #[tokio::test]
+    #[ignore]
+    async fn test_transaction_with_retry() {
+        let db = std::env::var("THORN_TEST_DB").expect("THORN_TEST_DB is not set");
+        let (mut client, conn) = pgt::connect(&db, pgt::NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        let policy = RetryPolicy {
+            isolation_level: Some(IsolationLevel::Serializable),
+            ..RetryPolicy::default()
         };
 
-        let stmt = self.prepare_typed(q, tys).await?;
+        let mut calls = 0;
+        let isolation = transaction_with_retry(&mut client, policy, async |tx| {
+            calls += 1;
+
+            // fails the first attempt as if it conflicted with another transaction
+            if calls == 1 {
+                tx.batch_execute("DO $$ BEGIN RAISE EXCEPTION USING ERRCODE = '40001'; END $$").await?;
+            }
 
-        let stream = self.query_raw(&stmt, slice_iter(&query.params)).await?;
+            Ok(tx.query_one("SHOW transaction_isolation", &[]).await?.get::<_, String>(0))
+        })
+        .await
+        .unwrap();
 
-        Ok(stream.map(|r| match r {
-            Ok(row) => Ok(E::from(row)),
-            Err(e) => Err(e.into()),
-        }))
+        assert_eq!(calls, 2);
+        assert_eq!(isolation, "serializable");
     }
 }
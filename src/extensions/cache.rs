@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pg::Type;
+use pgt::{error::SqlState, Statement};
+
+use crate::macros::{Query, RowColumns};
+
+/// Key used to look up a prepared statement in the [`StatementCache`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// Address of a `&'static StaticQuery`, which is unique per `sql!` invocation
+    Static(usize),
+
+    /// Query text and parameter types of a dynamic query
+    Dynamic(String, Vec<Type>),
+}
+
+impl CacheKey {
+    /// Computes the cache key for a query, or `None` if the query is dynamic
+    /// and `cache_dynamic` is false.
+    pub fn for_query<E: RowColumns>(query: &Query<'_, E>, cache_dynamic: bool) -> Option<CacheKey> {
+        match query.cached {
+            Some(cached) => Some(CacheKey::Static(cached as *const _ as usize)),
+            None if cache_dynamic => Some(CacheKey::Dynamic(query.q.clone(), query.param_tys.clone())),
+            None => None,
+        }
+    }
+}
+
+/// Snapshot of [`StatementCache`] counters, suitable for exporting as metrics
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub invalidations: u64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+struct CacheEntry<V> {
+    value: V,
+    last_used: u64,
+}
+
+/// Least-recently-used map underlying [`StatementCache`]
+struct Lru<V> {
+    entries: HashMap<CacheKey, CacheEntry<V>>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            entries: HashMap::with_capacity(capacity),
+            tick: 0,
+            stats: CacheStats {
+                capacity,
+                ..CacheStats::default()
+            },
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<V> {
+        self.tick += 1;
+
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.tick;
+                self.stats.hits += 1;
+                Some(entry.value.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: CacheKey, value: V) {
+        if self.stats.capacity == 0 {
+            return;
+        }
+
+        self.tick += 1;
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.stats.capacity {
+            // linear scan is fine for the small capacities typical of statement caches
+            let lru = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone());
+
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+                self.stats.evictions += 1;
+            }
+        }
+
+        let last_used = self.tick;
+        self.entries.insert(key, CacheEntry { value, last_used });
+    }
+
+    fn invalidate(&mut self, key: &CacheKey) -> bool {
+        let removed = self.entries.remove(key).is_some();
+        if removed {
+            self.stats.invalidations += 1;
+        }
+        removed
+    }
+
+    fn clear(&mut self) {
+        self.stats.invalidations += self.entries.len() as u64;
+        self.entries.clear();
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.len(),
+            ..self.stats
+        }
+    }
+}
+
+/// Per-connection LRU cache of prepared statements.
+///
+/// Statements are only valid on the connection that prepared them, so each
+/// connection must have its own cache.
+pub struct StatementCache {
+    inner: Mutex<Lru<Statement>>,
+    cache_dynamic: bool,
+}
+
+impl StatementCache {
+    /// Create a new cache holding at most `capacity` statements. Only static queries are cached.
+    pub fn new(capacity: usize) -> Self {
+        StatementCache {
+            inner: Mutex::new(Lru::new(capacity)),
+            cache_dynamic: false,
+        }
+    }
+
+    /// Also cache dynamic queries, keyed on their text and parameter types
+    pub fn with_dynamic(mut self, cache_dynamic: bool) -> Self {
+        self.cache_dynamic = cache_dynamic;
+        self
+    }
+
+    #[inline]
+    pub fn caches_dynamic(&self) -> bool {
+        self.cache_dynamic
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<Statement> {
+        self.inner.lock().unwrap().get(key)
+    }
+
+    pub fn insert(&self, key: CacheKey, stmt: Statement) {
+        self.inner.lock().unwrap().insert(key, stmt)
+    }
+
+    /// Remove a single statement from the cache, returning true if it was present.
+    pub fn invalidate(&self, key: &CacheKey) -> bool {
+        self.inner.lock().unwrap().invalidate(key)
+    }
+
+    /// Remove all statements from the cache, such as after running migrations.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().clear()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap().stats()
+    }
+
+    /// If the statement for `key` is `stale` on the server, invalidate it and
+    /// return true to prepare it again, but only once per execution.
+    pub(crate) fn retry_stale(&self, key: Option<&CacheKey>, stale: bool, retried: &mut bool) -> bool {
+        match key {
+            Some(key) if stale && !*retried => {
+                self.invalidate(key);
+                *retried = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns true if the error indicates a cached statement is no longer valid on the server,
+/// such as `cached plan must not change result type` after a schema change.
+pub fn is_stale_statement_error(err: &pgt::Error) -> bool {
+    match err.code() {
+        Some(code) => is_stale_statement(code, err.as_db_error().map_or("", |db| db.message())),
+        None => false,
+    }
+}
+
+fn is_stale_statement(code: &SqlState, message: &str) -> bool {
+    match *code {
+        SqlState::INVALID_SQL_STATEMENT_NAME => true,
+        SqlState::FEATURE_NOT_SUPPORTED => message.starts_with("cached plan must not change result type"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::macros::StaticQuery;
+
+    fn key(n: usize) -> CacheKey {
+        CacheKey::Static(n)
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut lru = Lru::new(2);
+
+        lru.insert(key(1), 1);
+        lru.insert(key(2), 2);
+
+        // touching 1 makes 2 the least recently used
+        assert_eq!(lru.get(&key(1)), Some(1));
+
+        lru.insert(key(3), 3);
+        assert_eq!(lru.get(&key(2)), None);
+        assert_eq!(lru.get(&key(1)), Some(1));
+        assert_eq!(lru.get(&key(3)), Some(3));
+
+        // replacing an existing key does not evict
+        lru.insert(key(3), 30);
+        assert_eq!(lru.get(&key(1)), Some(1));
+        assert_eq!(lru.get(&key(3)), Some(30));
+
+        assert!(lru.invalidate(&key(1)));
+        assert!(!lru.invalidate(&key(1)));
+
+        lru.insert(key(4), 4);
+        lru.clear();
+
+        let stats = lru.stats();
+        assert_eq!(
+            stats,
+            CacheStats {
+                hits: 5,
+                misses: 1,
+                evictions: 1,
+                invalidations: 3,
+                len: 0,
+                capacity: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lru_zero_capacity() {
+        let mut lru = Lru::new(0);
+
+        lru.insert(key(1), 1);
+        assert_eq!(lru.get(&key(1)), None);
+        assert_eq!(lru.stats().len, 0);
+        assert_eq!(lru.stats().evictions, 0);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut dynamic = Query::<pgt::Row>::default();
+        dynamic.q.push_str("SELECT $1");
+        dynamic.param_tys.push(Type::INT4);
+
+        assert_eq!(CacheKey::for_query(&dynamic, false), None);
+        assert_eq!(
+            CacheKey::for_query(&dynamic, true),
+            Some(CacheKey::Dynamic("SELECT $1".to_owned(), vec![Type::INT4]))
+        );
+
+        let mut other = Query::<pgt::Row>::default();
+        other.q.push_str("SELECT $1");
+        other.param_tys.push(Type::INT8);
+
+        assert_ne!(CacheKey::for_query(&dynamic, true), CacheKey::for_query(&other, true));
+
+        // static queries are keyed on their address, whatever their text
+        let a: &'static StaticQuery<pgt::Row> = Box::leak(Box::new(StaticQuery::from(dynamic)));
+        let b: &'static StaticQuery<pgt::Row> = Box::leak(Box::new(StaticQuery::from(other)));
+
+        let key_a = CacheKey::for_query(&Query::__from_cached(a, Vec::new()), false);
+
+        assert_eq!(key_a, Some(CacheKey::Static(a as *const _ as usize)));
+        assert_eq!(key_a, CacheKey::for_query(&Query::__from_cached(a, Vec::new()), true));
+        assert_ne!(key_a, CacheKey::for_query(&Query::__from_cached(b, Vec::new()), false));
+    }
+
+    #[test]
+    fn test_retry_stale() {
+        let cache = StatementCache::new(4);
+        let mut retried = false;
+
+        assert!(!cache.retry_stale(Some(&key(1)), false, &mut retried));
+        assert!(!cache.retry_stale(None, true, &mut retried));
+
+        assert!(cache.retry_stale(Some(&key(1)), true, &mut retried));
+        assert!(retried);
+
+        // only retried once
+        assert!(!cache.retry_stale(Some(&key(1)), true, &mut retried));
+
+        assert!(is_stale_statement(&SqlState::INVALID_SQL_STATEMENT_NAME, ""));
+        assert!(is_stale_statement(
+            &SqlState::FEATURE_NOT_SUPPORTED,
+            "cached plan must not change result type"
+        ));
+        assert!(!is_stale_statement(&SqlState::FEATURE_NOT_SUPPORTED, "something else"));
+        assert!(!is_stale_statement(&SqlState::UNIQUE_VIOLATION, ""));
+    }
+}
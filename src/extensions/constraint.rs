@@ -0,0 +1,247 @@
+use std::fmt;
+
+use pgt::Error as PgError;
+
+use crate::name::Schema;
+use crate::table::TableExt;
+
+/// Details of a unique, foreign-key, not-null or check constraint violation,
+/// with the names reported by the database.
+#[derive(Debug)]
+pub struct ConstraintViolation {
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    /// Columns of `table` involved in the violation, from the error column or the key in the error detail
+    pub columns: Vec<String>,
+    pub constraint: Option<String>,
+    /// For foreign key violations on insert or update, the table that is missing the key
+    pub referenced_table: Option<String>,
+    /// For foreign key violations on delete or update of the referenced table, the key columns
+    /// of that table, which is not named by the error.
+    pub referenced_columns: Vec<String>,
+    pub source: PgError,
+}
+
+impl ConstraintViolation {
+    pub(crate) fn new(err: PgError) -> Result<Box<Self>, PgError> {
+        let Some(db) = err.as_db_error() else { return Err(err) };
+
+        let detail = db.detail().unwrap_or_default();
+
+        let mut columns = match db.column() {
+            Some(column) => vec![column.to_owned()],
+            None => Vec::new(),
+        };
+
+        let detail = parse_detail(detail);
+
+        let mut referenced_table = None;
+        let mut referenced_columns = Vec::new();
+
+        // Here the error table is the referencing table, and the key belongs to the referenced table
+        if detail.still_referenced {
+            referenced_columns = detail.key;
+        } else {
+            if columns.is_empty() {
+                columns = detail.key;
+            }
+
+            referenced_table = detail.missing_from;
+        }
+
+        Ok(Box::new(ConstraintViolation {
+            schema: db.schema().map(str::to_owned),
+            table: db.table().map(str::to_owned),
+            columns,
+            constraint: db.constraint().map(str::to_owned),
+            referenced_table,
+            referenced_columns,
+            source: err,
+        }))
+    }
+
+    /// Returns true if the violation occurred on the given table
+    pub fn is_on<T: TableExt>(&self) -> bool {
+        if self.table.as_deref() != Some(T::NAME.name()) {
+            return false;
+        }
+
+        match T::SCHEMA {
+            Schema::Named(schema) => self.schema.as_deref() == Some(schema),
+            Schema::None => true,
+        }
+    }
+
+    /// The first column of the violation as a column of `T`, if the violation occurred on `T`.
+    ///
+    /// ```ignore
+    /// match err {
+    ///     Error::UniqueViolation(v) if v.column::<Users>() == Some(Users::UserName) => { ... }
+    ///     _ => { ... }
+    /// }
+    /// ```
+    pub fn column<T: TableExt>(&self) -> Option<T> {
+        self.columns_of::<T>().next()
+    }
+
+    /// All columns of the violation as columns of `T`, if the violation occurred on `T`.
+    pub fn columns_of<T: TableExt>(&self) -> impl Iterator<Item = T> + '_ {
+        let on = self.is_on::<T>();
+
+        self.columns
+            .iter()
+            .filter(move |_| on)
+            .filter_map(|name| T::COLUMNS.iter().copied().find(|c| c.name() == name))
+    }
+
+    /// Resolve the violating table to its `tables!` definition
+    #[cfg(feature = "registry")]
+    pub fn registered_table(&self) -> Option<&'static crate::registry::RegisteredTable> {
+        crate::registry::find_table(self.schema.as_deref(), self.table.as_deref()?)
+    }
+
+    /// Resolve the violating columns to their `tables!` definitions
+    #[cfg(feature = "registry")]
+    pub fn registered_columns(&self) -> Vec<&'static (dyn crate::table::Column + Sync)> {
+        match self.registered_table() {
+            Some(table) => self.columns.iter().filter_map(|c| table.column(c)).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref schema) = self.schema {
+            write!(f, "{schema}.")?;
+        }
+
+        f.write_str(self.table.as_deref().unwrap_or("<unknown>"))?;
+
+        if !self.columns.is_empty() {
+            write!(f, " ({})", self.columns.join(", "))?;
+        }
+
+        if let Some(ref constraint) = self.constraint {
+            write!(f, " violates \"{constraint}\"")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parts of the detail message of a constraint violation
+#[derive(Debug, Default, PartialEq)]
+struct Detail {
+    /// Columns of the key, from `Key (a, b)=(1, 2) already exists.`
+    key: Vec<String>,
+    /// `Key (id)=(1) is still referenced from table "messages".`
+    still_referenced: bool,
+    /// Table from `Key (author)=(5) is not present in table "users".`
+    missing_from: Option<String>,
+}
+
+fn parse_detail(detail: &str) -> Detail {
+    let key = match detail.strip_prefix("Key (").and_then(|d| d.split_once(")=(")) {
+        Some((key, _)) => split_key(key).into_iter().map(unquote_ident).collect(),
+        None => Vec::new(),
+    };
+
+    let missing_from = detail
+        .split_once(" is not present in table \"")
+        .and_then(|(_, rest)| rest.rsplit_once('"'))
+        .map(|(table, _)| table.replace("\"\"", "\""));
+
+    Detail {
+        key,
+        still_referenced: detail.contains(" is still referenced from table "),
+        missing_from,
+    }
+}
+
+/// Split key columns on commas outside of quoted identifiers and expressions
+fn split_key(key: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in key.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                parts.push(key[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(key[start..].trim());
+    parts
+}
+
+fn unquote_ident(ident: &str) -> String {
+    match ident.strip_prefix('"').and_then(|i| i.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => ident.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_detail() {
+        let cases: &[(&str, &[&str], bool, Option<&str>)] = &[
+            ("Key (id)=(1) already exists.", &["id"], false, None),
+            (
+                r#"Key ("Org Id", "we""ird")=(2, x, y) already exists."#,
+                &["Org Id", "we\"ird"],
+                false,
+                None,
+            ),
+            (r#"Key ("a, b", c)=(1, 2) already exists."#, &["a, b", "c"], false, None),
+            (
+                r#"Key (lower("we""ird"))=(x, y) already exists."#,
+                &[r#"lower("we""ird")"#],
+                false,
+                None,
+            ),
+            ("Key (a, b)=(1, a)=(b) already exists.", &["a", "b"], false, None),
+            (
+                r#"Key (author)=(5) is not present in table "users"."#,
+                &["author"],
+                false,
+                Some("users"),
+            ),
+            (
+                r#"Key (a, b)=(2, q) is not present in table "Pair"."#,
+                &["a", "b"],
+                false,
+                Some("Pair"),
+            ),
+            (
+                r#"Key (id)=(1) is still referenced from table "msgs"."#,
+                &["id"],
+                true,
+                None,
+            ),
+            ("Failing row contains (1, null).", &[], false, None),
+            ("", &[], false, None),
+        ];
+
+        for &(detail, key, still_referenced, missing_from) in cases {
+            let expected = Detail {
+                key: key.iter().map(|k| k.to_string()).collect(),
+                still_referenced,
+                missing_from: missing_from.map(str::to_owned),
+            };
+
+            assert_eq!(parse_detail(detail), expected, "{detail}");
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::fmt::{self, Write};
+
+use pgt::Row;
+use serde::Deserialize;
+
+use super::Error;
+use crate::macros::{Query, RowColumns, SourceLocation};
+
+/// Options for [`ClientExt::explain2`](super::ClientExt::explain2)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExplainOptions {
+    /// Run the query and report actual row counts and timings.
+    ///
+    /// Note that this executes the query, including any data modifications.
+    pub analyze: bool,
+    /// Report buffer usage, requires `analyze`
+    pub buffers: bool,
+    /// Report schemas, output columns and other details
+    pub verbose: bool,
+}
+
+impl fmt::Display for ExplainOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EXPLAIN (FORMAT JSON")?;
+
+        for (enabled, option) in [
+            (self.analyze, "ANALYZE"),
+            (self.buffers, "BUFFERS"),
+            (self.verbose, "VERBOSE"),
+        ] {
+            if enabled {
+                write!(f, ", {option}")?;
+            }
+        }
+
+        f.write_char(')')
+    }
+}
+
+/// Output of `EXPLAIN (FORMAT JSON)`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Explain {
+    #[serde(rename = "Plan")]
+    pub plan: PlanNode,
+
+    /// Planning time in milliseconds, with `analyze`
+    #[serde(rename = "Planning Time")]
+    pub planning_time: Option<f64>,
+
+    /// Execution time in milliseconds, with `analyze`
+    #[serde(rename = "Execution Time")]
+    pub execution_time: Option<f64>,
+}
+
+/// A node of the query plan tree
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanNode {
+    /// Such as `Seq Scan`, `Index Scan` or `Hash Join`
+    #[serde(rename = "Node Type")]
+    pub node_type: String,
+
+    /// Table scanned by this node
+    #[serde(rename = "Relation Name")]
+    pub relation: Option<String>,
+
+    /// Schema of the scanned table, with `verbose`
+    #[serde(rename = "Schema")]
+    pub schema: Option<String>,
+
+    #[serde(rename = "Alias")]
+    pub alias: Option<String>,
+
+    #[serde(rename = "Index Name")]
+    pub index: Option<String>,
+
+    #[serde(rename = "Startup Cost")]
+    pub startup_cost: f64,
+
+    #[serde(rename = "Total Cost")]
+    pub total_cost: f64,
+
+    /// Estimated number of rows output by this node
+    #[serde(rename = "Plan Rows")]
+    pub plan_rows: f64,
+
+    /// Actual number of rows output by this node per loop, with `analyze`
+    #[serde(rename = "Actual Rows")]
+    pub actual_rows: Option<f64>,
+
+    #[serde(rename = "Actual Loops")]
+    pub actual_loops: Option<f64>,
+
+    /// Child nodes
+    #[serde(rename = "Plans", default)]
+    pub plans: Vec<PlanNode>,
+
+    /// All other properties of the node, such as filters and buffer usage
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PlanNode {
+    /// Iterate over this node and all of its descendants, depth-first
+    pub fn nodes(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack = vec![self];
+
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.plans.iter().rev());
+            Some(node)
+        })
+    }
+
+    #[inline]
+    pub fn is_seq_scan(&self) -> bool {
+        self.node_type == "Seq Scan"
+    }
+}
+
+/// Wrap the query in `EXPLAIN`, keeping its parameters
+pub(crate) fn wrap<'a, E: RowColumns>(query: &Query<'a, E>, opts: ExplainOptions) -> Query<'a, Row> {
+    Query {
+        q: format!("{opts} {}", query.sql()),
+        param_tys: query.param_types().to_vec(),
+        params: query.params.clone(),
+        location: query.location,
+        ..Query::default()
+    }
+}
+
+pub(crate) fn parse(row: &Row, location: SourceLocation) -> Result<Explain, Error> {
+    let pg::Json(mut explain): pg::Json<Vec<Explain>> = row.try_get(0)?;
+
+    explain.pop().ok_or(Error::NoRows(location))
+}
+
+#[cfg(feature = "registry")]
+pub use self::check::{check_plans, PlanReport, SeqScan};
+
+#[cfg(feature = "registry")]
+mod check {
+    use std::collections::HashMap;
+    use std::fmt;
+
+    use pg::Type;
+    use pgt::error::ErrorPosition;
+
+    use super::{parse, ExplainOptions};
+    use crate::escape;
+    use crate::extensions::{ClientExt, Error, SqlError};
+    use crate::macros::SqlFormatError;
+    use crate::registry::{queries, RegisteredQuery, WarmupError};
+    use crate::source_map::{SourceMap, SqlSpan};
+
+    crate::tables! {
+        struct PgClass in PgCatalog {
+            Oid: Type::OID,
+            Reltuples: Type::FLOAT4,
+        }
+    }
+
+    const STATEMENT: &str = "__thorn_check_plan";
+
+    /// A sequential scan over a large table, found by [`check_plans`]
+    #[derive(Debug)]
+    pub struct SeqScan {
+        pub query: &'static RegisteredQuery,
+        pub schema: Option<String>,
+        pub relation: String,
+        /// Estimated number of rows in the table
+        pub rows: f64,
+    }
+
+    impl fmt::Display for SeqScan {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Query at {} sequentially scans ", self.query)?;
+
+            if let Some(ref schema) = self.schema {
+                write!(f, "{schema}.")?;
+            }
+
+            write!(f, "{} (~{} rows)", self.relation, self.rows)
+        }
+    }
+
+    /// Result of [`check_plans`]
+    #[derive(Debug, Default)]
+    pub struct PlanReport {
+        pub seq_scans: Vec<SeqScan>,
+        /// Queries that could not be planned
+        pub errors: Vec<WarmupError>,
+    }
+
+    impl PlanReport {
+        /// Returns true if no sequential scans or errors were found
+        pub fn is_ok(&self) -> bool {
+            self.seq_scans.is_empty() && self.errors.is_empty()
+        }
+    }
+
+    impl fmt::Display for PlanReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for scan in &self.seq_scans {
+                writeln!(f, "{scan}")?;
+            }
+
+            for error in &self.errors {
+                writeln!(f, "{error}")?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Explains every registered static query with a generic plan, as if its parameters were unknown,
+    /// and reports sequential scans on tables with more than `min_rows` estimated rows.
+    ///
+    /// Intended for tests against a local database with a representative schema and statistics,
+    /// so run `ANALYZE` after loading data. Queries defined within thorn itself are skipped.
+    ///
+    /// ```ignore
+    /// let report = thorn::extensions::check_plans(&client, 10_000.0).await?;
+    /// assert!(report.is_ok(), "{report}");
+    /// ```
+    pub async fn check_plans(client: &pgt::Client, min_rows: f64) -> Result<PlanReport, Error> {
+        // plan as if parameter values are unknown, instead of using the NULLs passed to EXECUTE
+        client.batch_execute("SET plan_cache_mode = force_generic_plan").await?;
+
+        let res = check_registered(client, min_rows).await;
+
+        // always restore the setting, as the connection may be reused from a pool
+        let reset = client.batch_execute("RESET plan_cache_mode").await;
+
+        let report = res?;
+        reset?;
+
+        Ok(report)
+    }
+
+    async fn check_registered(client: &pgt::Client, min_rows: f64) -> Result<PlanReport, Error> {
+        let mut report = PlanReport::default();
+        let mut sizes: HashMap<(Option<String>, String), f64> = HashMap::new();
+
+        for query in queries() {
+            if query.module_path == "thorn" || query.module_path.starts_with("thorn::") {
+                continue;
+            }
+
+            let explain = match explain_generic(client, query).await {
+                Ok(explain) => explain,
+                Err(error) => {
+                    report.errors.push(WarmupError { query, error });
+                    continue;
+                }
+            };
+
+            for node in explain.plan.nodes().filter(|node| node.is_seq_scan()) {
+                let Some(ref relation) = node.relation else { continue };
+
+                let key = (node.schema.clone(), relation.clone());
+
+                let rows = match sizes.get(&key) {
+                    Some(&rows) => rows,
+                    None => {
+                        let rows = table_rows(client, key.0.as_deref(), relation).await?.unwrap_or(node.plan_rows);
+                        *sizes.entry(key).or_insert(rows)
+                    }
+                };
+
+                if rows > min_rows {
+                    report.seq_scans.push(SeqScan {
+                        query,
+                        schema: node.schema.clone(),
+                        relation: relation.clone(),
+                        rows,
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn explain_generic(client: &pgt::Client, query: &RegisteredQuery) -> Result<super::Explain, Error> {
+        let (sql, tys) = query.sql()?;
+
+        let mut prepare = format!("PREPARE {STATEMENT}");
+        let opts = ExplainOptions {
+            verbose: true,
+            ..ExplainOptions::default()
+        };
+
+        let mut execute = format!("{opts} EXECUTE {STATEMENT}");
+
+        for (i, ty) in tys.iter().enumerate() {
+            let sep = if i == 0 { " (" } else { ", " };
+
+            prepare.push_str(sep);
+            match *ty {
+                Type::ANY => prepare.push_str("unknown"),
+                _ => {
+                    let _ = crate::debug_sql::write_type(&mut prepare, ty);
+                }
+            }
+
+            execute.push_str(sep);
+            execute.push_str("NULL");
+        }
+
+        if !tys.is_empty() {
+            prepare.push(')');
+            execute.push(')');
+        }
+
+        prepare.push_str(" AS ");
+        prepare.push_str(sql);
+
+        if let Err(err) = client.batch_execute(&prepare).await {
+            return Err(prepare_error(err, &prepare, sql, query));
+        }
+
+        let res = client.query_one(&execute, &[]).await;
+
+        // deallocate even if EXECUTE failed, so the statement is not left on the connection
+        let deallocate = client.batch_execute(&format!("DEALLOCATE {STATEMENT}")).await;
+
+        let row = res?;
+        deallocate?;
+
+        parse(&row, query.location)
+    }
+
+    /// Point the error at the query text rather than the `PREPARE` statement wrapping it
+    fn prepare_error(err: pgt::Error, prepare: &str, sql: &str, query: &RegisteredQuery) -> Error {
+        let prefix = prepare[..prepare.len() - sql.len()].chars().count();
+
+        match err.as_db_error().and_then(|db| db.position()) {
+            Some(&ErrorPosition::Original(position)) if position as usize > prefix => {
+                let map = SourceMap::default();
+                let span = SqlSpan::from_position(sql, &map, query.location, position as usize - prefix);
+
+                Error::Sql(Box::new(SqlError { source: err, span }))
+            }
+            _ => Error::from(err),
+        }
+    }
+
+    /// Estimated number of rows in the table, if the table has been analyzed
+    async fn table_rows(client: &pgt::Client, schema: Option<&str>, relation: &str) -> Result<Option<f64>, Error> {
+        let mut name = String::new();
+
+        if let Some(schema) = schema {
+            escape::write_identifier(schema, &mut name).map_err(SqlFormatError::from)?;
+            name.push('.');
+        }
+
+        escape::write_identifier(relation, &mut name).map_err(SqlFormatError::from)?;
+
+        let row = client
+            .query_opt2(sql! {
+                SELECT PgClass.Reltuples AS @Reltuples
+                FROM PgClass WHERE PgClass.Oid = to_regclass(#{&name as Type::TEXT})
+            })
+            .await?;
+
+        Ok(match row {
+            Some(row) => Some(row.reltuples::<f32>()? as f64).filter(|&rows| rows >= 0.0),
+            None => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_parse() {
+        let opts = ExplainOptions {
+            analyze: true,
+            verbose: true,
+            ..ExplainOptions::default()
+        };
+
+        assert_eq!(opts.to_string(), "EXPLAIN (FORMAT JSON, ANALYZE, VERBOSE)");
+
+        let json = r#"[{"Plan": {"Node Type": "Hash Join", "Startup Cost": 1.0, "Total Cost": 2.0, "Plan Rows": 10,
+            "Plans": [{"Node Type": "Seq Scan", "Relation Name": "users", "Startup Cost": 0.0, "Total Cost": 1.0,
+            "Plan Rows": 100, "Filter": "(id > 1)"}]}, "Planning Time": 0.1}]"#;
+
+        let explain: Vec<Explain> = serde_json::from_str(json).unwrap();
+        let nodes: Vec<_> = explain[0].plan.nodes().map(|node| node.node_type.as_str()).collect();
+
+        assert_eq!(nodes, ["Hash Join", "Seq Scan"]);
+        assert_eq!(explain[0].planning_time, Some(0.1));
+
+        let scan = explain[0].plan.nodes().find(|node| node.is_seq_scan()).unwrap();
+        assert_eq!(scan.relation.as_deref(), Some("users"));
+        assert_eq!(scan.extra["Filter"], "(id > 1)");
+    }
+}
@@ -1,19 +1,40 @@
-use heck::*;
-use name::Schema;
+//! Schema generation from a live database or SQL DDL.
+//!
+//! [`introspect`] collects the tables, enums, ranges, composite types and functions of a database into a
+//! [`SchemaModel`],
+//! and [`parse_ddl`] does the same from SQL DDL without a database. An [`Emitter`] then turns the model
+//! into Rust definitions ([`RustEmitter`]), JSON ([`JsonEmitter`]) or Markdown documentation
+//! ([`MarkdownEmitter`]).
 
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt::{self, Write};
 
 use pg::{Oid, Type};
 use pgt::Error as PgError;
 
 use crate::extensions::{ClientExt, Error as ExtError};
 
+mod config;
+mod ddl;
+mod json;
+mod markdown;
+mod model;
+mod rust;
+
+pub use config::Config;
+pub use ddl::parse_ddl;
+pub use json::JsonEmitter;
+pub use markdown::MarkdownEmitter;
+pub use model::*;
+pub use rust::RustEmitter;
+
 crate::tables! {
     struct PgAttribute {
         Attrelid: Type::OID,
+        Attnum: Type::INT2,
         Attname: Type::NAME,
+        Atttypid: Type::OID,
+        Attisdropped: Type::BOOL,
     }
 
     struct PgType in PgCatalog {
@@ -21,6 +42,9 @@ crate::tables! {
         Typname: Type::NAME,
         Typelem: Type::OID,
         Typnamespace: Type::OID,
+        Typbasetype: Type::OID,
+        Typarray: Type::OID,
+        Typrelid: Type::OID,
     }
 
     struct PgEnum in PgCatalog {
@@ -41,6 +65,12 @@ crate::tables! {
         Description: Type::TEXT,
     }
 
+    struct PgRange in PgCatalog {
+        Rngtypid: Type::OID,
+        Rngsubtype: Type::OID,
+        Rngmultitypid: Type::OID,
+    }
+
     struct PgProc in PgCatalog {
         Oid: Type::OID,
         Proname: Type::NAME,
@@ -51,6 +81,21 @@ crate::tables! {
         Proargnames: Type::TEXT_ARRAY,
     }
 
+    struct PgClass in PgCatalog {
+        Oid: Type::OID,
+        Relname: Type::NAME,
+        Relnamespace: Type::OID,
+        Relkind: Type::CHAR,
+    }
+
+    struct PgConstraint in PgCatalog {
+        Conname: Type::NAME,
+        Contype: Type::CHAR,
+        Conrelid: Type::OID,
+        Confrelid: Type::OID,
+        Conkey: Type::INT2_ARRAY,
+        Confkey: Type::INT2_ARRAY,
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -63,95 +108,210 @@ pub enum Error {
 
     #[error(transparent)]
     Ext(#[from] ExtError),
-}
 
-struct Proc<'a> {
-    name: &'a str,
-    argnames: Vec<Cow<'a, str>>,
-    argtypes: Vec<Oid>,
-    rettype: Oid,
-    comment: Option<&'a str>,
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("DDL error at line {line}: {message}")]
+    Ddl { line: usize, message: String },
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Cannot find type \"{ty}\" for {context}, it can be mapped in the `types` config")]
+    UnknownType { ty: String, context: String },
 }
 
-#[derive(Debug)]
-struct Variant<'a> {
-    oid: Oid,
-    name: &'a str,
-    position: f32,
+/// Catalog entry of a type, see [`resolve_type`]
+struct TypeInfo {
+    ty: TypeRef,
+    /// Base type of a domain
+    basetype: Oid,
+    /// Element type of an array
+    elem: Oid,
+    /// Array type of this type
+    array: Oid,
 }
 
-#[derive(Debug)]
-struct Enum<'a> {
-    oid: Oid,
-    name: &'a str,
-    comment: Option<&'a str>,
-    variants: Vec<Variant<'a>>,
+/// Reference to the type `oid`, with domains and arrays of domains resolved to their base types
+fn resolve_type(types: &HashMap<Oid, TypeInfo>, oid: Oid) -> TypeRef {
+    let Some(info) = types.get(&oid) else {
+        return TypeRef::new(oid, "", "");
+    };
+
+    if info.basetype != 0 {
+        return resolve_type(types, info.basetype);
+    }
+
+    if let Some(elem) = types.get(&info.elem).filter(|elem| elem.basetype != 0) {
+        let base = resolve_type(types, elem.basetype);
+
+        if let Some(array) = types.get(&base.oid).and_then(|base| types.get(&base.array)) {
+            return array.ty.clone();
+        }
+    }
+
+    info.ty.clone()
 }
 
-struct Column<'a> {
-    name: &'a str,
-    null: bool,
-    udt: &'a str,
-    ty: Oid,
-    position: i32,
-    comment: Option<&'a str>,
+/// Output format for a [`SchemaModel`]
+pub trait Emitter {
+    fn emit(&self, model: &SchemaModel) -> Result<String, Error>;
 }
 
-struct Table<'a> {
-    comment: Option<&'a str>,
-    name: &'a str,
-    cols: Vec<Column<'a>>,
+use crate::{sql, table::SchemaColumns};
+
+/// Generate `thorn` definitions for the tables, enums, ranges, composite types and functions of the given schemas.
+///
+/// Shorthand for [`introspect`] followed by [`RustEmitter`].
+pub async fn generate(client: &pgt::Client, schemas: &[String], config: &Config) -> Result<String, Error> {
+    RustEmitter::new(config.clone()).emit(&introspect(client, schemas).await?)
 }
 
-use crate::{table::SchemaColumns, *};
+/// Collect the tables, enums, ranges, composite types and functions of the given schemas.
+///
+/// If `schemas` is empty, all non-system schemas are used.
+pub async fn introspect(client: &pgt::Client, schemas: &[String]) -> Result<SchemaModel, Error> {
+    let mut schemas = schemas.to_vec();
+
+    if schemas.is_empty() {
+        #[rustfmt::skip]
+        let schemas_rows = client.query2(sql! {
+            const _: () = assert!(!Columns::IS_DYNAMIC);
+
+            SELECT PgNamespace.Nspname AS @Nspname
+            FROM PgNamespace
+            WHERE NOT starts_with(PgNamespace.Nspname, "pg_")
+                AND PgNamespace.Nspname != "information_schema"
+        }).await?;
+
+        for row in &schemas_rows {
+            let name: &str = row.nspname()?;
+
+            schemas.push(name.to_owned());
+        }
+    }
 
-const COMMENT_WIDTH: usize = 70;
+    schemas.sort();
+    schemas.dedup();
 
-pub async fn generate(client: &pgt::Client, schema: Option<String>) -> Result<String, Error> {
     #[rustfmt::skip]
     let columns_rows = client.query2(sql! {
         const _: () = assert!(!Columns::IS_DYNAMIC);
 
         SELECT
+            SchemaColumns.TableSchema AS @TableSchema,
             SchemaColumns.TableName AS @TableName,
             SchemaColumns.ColumnName AS @ColumnName,
-            SchemaColumns.UdtName AS @UdtName,
             SchemaColumns.OrdinalPosition AS @Position,
             SchemaColumns.IsNullable::BOOL AS @Nullable,
             PgType.Oid AS @Oid,
 
-            // pg_catalog.obj_description(to_regclass("columns"."table_schema" || '.' || "columns"."table_name")::oid)
+            // pg_catalog.obj_description(to_regclass(quote_ident("columns"."table_schema") || '.' || quote_ident("columns"."table_name"))::oid)
             pg_catalog.obj_description(
-                to_regclass(SchemaColumns.TableSchema || "." || SchemaColumns.TableName)::OID
+                to_regclass(quote_ident(SchemaColumns.TableSchema) || "." || quote_ident(SchemaColumns.TableName))::OID
             ) AS @TableComment,
 
-            // pg_catalog.col_description(to_regclass("columns"."table_schema" || '.' || "columns"."table_name")::oid, "columns"."ordinal_position")
+            // pg_catalog.col_description(to_regclass(quote_ident("columns"."table_schema") || '.' || quote_ident("columns"."table_name"))::oid, "columns"."ordinal_position")
             pg_catalog.col_description(
-                to_regclass(SchemaColumns.TableSchema || "." || SchemaColumns.TableName)::OID,
+                to_regclass(quote_ident(SchemaColumns.TableSchema) || "." || quote_ident(SchemaColumns.TableName))::OID,
                 SchemaColumns.OrdinalPosition
             ) AS @ColComment
 
-        FROM SchemaColumns LEFT JOIN PgType ON PgType.Typname = SchemaColumns.UdtName
-        WHERE SchemaColumns.TableSchema = #{&schema as SchemaColumns::TableSchema}
-        ORDER BY SchemaColumns.TableName ASC
+        FROM SchemaColumns
+            LEFT JOIN PgNamespace ON PgNamespace.Nspname = SchemaColumns.UdtSchema
+            LEFT JOIN PgType ON PgType.Typname = SchemaColumns.UdtName AND PgType.Typnamespace = PgNamespace.Oid
+        WHERE SchemaColumns.TableSchema = ANY(#{&schemas as Type::NAME_ARRAY})
+        ORDER BY SchemaColumns.TableName ASC, SchemaColumns.OrdinalPosition ASC
     })
     .await?;
 
+    #[rustfmt::skip]
+    let constraints_rows = client.query2(sql! {
+        const _: () = assert!(!Columns::IS_DYNAMIC);
+
+        SELECT
+            PgNamespace.Nspname AS @Nspname,
+            PgClass.Relname AS @Relname,
+            PgConstraint.Conname AS @Conname,
+            PgConstraint.Contype::TEXT AS @Contype,
+            PgConstraint.Conrelid AS @Conrelid,
+            PgConstraint.Confrelid AS @Confrelid,
+            PgConstraint.Conkey AS @Conkey,
+            PgConstraint.Confkey AS @Confkey,
+            (SELECT PgClass.Relname FROM PgClass WHERE PgClass.Oid = PgConstraint.Confrelid) AS @ForeignRelname,
+            (
+                SELECT PgNamespace.Nspname FROM PgNamespace
+                INNER JOIN PgClass ON PgClass.Relnamespace = PgNamespace.Oid
+                WHERE PgClass.Oid = PgConstraint.Confrelid
+            ) AS @ForeignNspname
+        FROM PgConstraint
+            INNER JOIN PgClass ON PgClass.Oid = PgConstraint.Conrelid
+            INNER JOIN PgNamespace ON PgNamespace.Oid = PgClass.Relnamespace
+        WHERE PgNamespace.Nspname = ANY(#{&schemas as Type::NAME_ARRAY})
+        ORDER BY PgConstraint.Conname ASC
+    }).await?;
+
     #[rustfmt::skip]
     let enums_rows = client.query2(sql! {
         const _: () = assert!(!Columns::IS_DYNAMIC);
 
         SELECT
             PgEnum.Enumtypid AS @Oid,
-            PgEnum.Oid AS @VariantOid,
+            PgType.Typarray AS @Typarray,
             PgType.Typname AS @Typname,
+            PgNamespace.Nspname AS @Nspname,
             PgEnum.Enumlabel AS @Enumlabel,
-            PgEnum.Enumsortorder AS @Enumsortorder,
             pg_catalog.obj_description(PgType.Oid) AS @Description
         FROM PgEnum
             INNER JOIN PgType ON PgType.Oid = PgEnum.Enumtypid
             LEFT JOIN PgNamespace ON PgNamespace.Oid = PgType.Typnamespace
-        WHERE PgNamespace.Nspname = #{&schema as PgNamespace::Nspname}
+        WHERE PgNamespace.Nspname = ANY(#{&schemas as Type::NAME_ARRAY})
+        ORDER BY PgEnum.Enumtypid ASC, PgEnum.Enumsortorder ASC
+    }).await?;
+
+    #[rustfmt::skip]
+    let ranges_rows = client.query2(sql! {
+        const _: () = assert!(!Columns::IS_DYNAMIC);
+
+        SELECT
+            PgRange.Rngtypid AS @Oid,
+            PgRange.Rngsubtype AS @Subtype,
+            PgRange.Rngmultitypid AS @MultiOid,
+            PgType.Typarray AS @Typarray,
+            PgType.Typname AS @Typname,
+            PgNamespace.Nspname AS @Nspname,
+            (SELECT PgType.Typname FROM PgType WHERE PgType.Oid = PgRange.Rngmultitypid) AS @MultiTypname,
+            (SELECT PgType.Typarray FROM PgType WHERE PgType.Oid = PgRange.Rngmultitypid) AS @MultiTyparray,
+            pg_catalog.obj_description(PgType.Oid) AS @Description
+        FROM PgRange
+            INNER JOIN PgType ON PgType.Oid = PgRange.Rngtypid
+            INNER JOIN PgNamespace ON PgNamespace.Oid = PgType.Typnamespace
+        WHERE PgNamespace.Nspname = ANY(#{&schemas as Type::NAME_ARRAY})
+    }).await?;
+
+    // standalone composite types, rather than the row types of tables
+    #[rustfmt::skip]
+    let composites_rows = client.query2(sql! {
+        const _: () = assert!(!Columns::IS_DYNAMIC);
+
+        SELECT
+            PgType.Oid AS @Oid,
+            PgType.Typarray AS @Typarray,
+            PgType.Typname AS @Typname,
+            PgNamespace.Nspname AS @Nspname,
+            PgAttribute.Attname AS @Attname,
+            PgAttribute.Atttypid AS @Atttypid,
+            pg_catalog.obj_description(PgType.Oid) AS @Description
+        FROM PgType
+            INNER JOIN PgNamespace ON PgNamespace.Oid = PgType.Typnamespace
+            INNER JOIN PgClass ON PgClass.Oid = PgType.Typrelid
+            INNER JOIN PgAttribute ON PgAttribute.Attrelid = PgClass.Oid
+        WHERE PgNamespace.Nspname = ANY(#{&schemas as Type::NAME_ARRAY})
+            AND PgClass.Relkind = "c"
+            AND PgAttribute.Attnum > 0
+            AND NOT PgAttribute.Attisdropped
+        ORDER BY PgType.Oid ASC, PgAttribute.Attnum ASC
     }).await?;
 
     #[rustfmt::skip]
@@ -162,297 +322,467 @@ pub async fn generate(client: &pgt::Client, schema: Option<String>) -> Result<St
 
         SELECT
             PgProc.Proname AS @Proname,
+            PgNamespace.Nspname AS @Nspname,
             PgProc.Proargnames AS @Proargnames,
             PgProc.Proargtypes AS @Proargtypes,
             PgDescription.Description AS @Description
         FROM PgProc
         INNER JOIN PgNamespace ON PgNamespace.Oid = PgProc.Pronamespace
         LEFT JOIN PgDescription ON PgDescription.Objoid = PgProc.Oid
-        WHERE PgNamespace.Nspname = #{&schema as PgNamespace::Nspname}
+        WHERE PgNamespace.Nspname = ANY(#{&schemas as Type::NAME_ARRAY})
             AND PgProc.Provariadic = 0
             AND PgProc.Prorettype != const { 2279_i32 }
     }).await?;
 
-    let mut tables = HashMap::new();
-    let mut enums = HashMap::new();
-    let mut procs = Vec::new();
-
-    let mut uses_nullable = false;
-    let mut uses_type = false;
+    // names of the columns referenced by constraints, by relation and attribute number
+    let mut relids: Vec<Oid> = Vec::new();
 
-    for row in &columns_rows {
-        let table_name: &str = row.table_name()?;
-        let column_name: &str = row.column_name()?;
-        let udt_name: &str = row.udt_name()?;
-        let nullable: bool = row.nullable()?;
-        let position = row.position()?;
-        let oid = row.oid()?;
-        let table_comment: Option<&str> = row.table_comment()?;
-        let col_comment: Option<&str> = row.col_comment()?;
+    for row in &constraints_rows {
+        relids.push(row.conrelid()?);
+        relids.push(row.confrelid()?);
+    }
 
-        let table = tables.entry(table_name).or_insert_with(|| Table {
-            name: table_name,
-            comment: table_comment,
-            cols: Vec::new(),
-        });
+    #[rustfmt::skip]
+    let attributes_rows = client.query2(sql! {
+        const _: () = assert!(!Columns::IS_DYNAMIC);
 
-        table.cols.push(Column {
-            name: column_name,
-            null: nullable,
-            udt: udt_name,
-            position,
-            comment: col_comment,
-            ty: oid,
-        });
-    }
+        SELECT
+            PgAttribute.Attrelid AS @Attrelid,
+            PgAttribute.Attnum AS @Attnum,
+            PgAttribute.Attname AS @Attname
+        FROM PgAttribute
+        WHERE PgAttribute.Attrelid = ANY(#{&relids as Type::OID_ARRAY}) AND PgAttribute.Attnum > 0
+    }).await?;
 
-    for row in &enums_rows {
-        let oid: Oid = row.oid()?;
-        let variant_oid: Oid = row.variant_oid()?;
-        let enum_name: &str = row.typname()?;
-        let enum_variant: &str = row.enumlabel()?;
-        let enum_position: f32 = row.enumsortorder()?;
-        let enum_comment: Option<&str> = row.description()?;
+    let mut attributes = HashMap::new();
 
-        let enum_ = enums.entry(enum_name).or_insert_with(|| Enum {
-            oid,
-            name: enum_name,
-            comment: enum_comment,
-            variants: Vec::new(),
-        });
+    for row in &attributes_rows {
+        let relid: Oid = row.attrelid()?;
+        let num: i16 = row.attnum()?;
+        let name: &str = row.attname()?;
 
-        enum_.variants.push(Variant {
-            oid: variant_oid,
-            name: enum_variant,
-            position: enum_position,
-        });
+        attributes.insert((relid, num), name);
     }
 
-    for row in &procs_rows {
-        let argnames: Option<Vec<&str>> = row.proargnames()?;
-        let argtypes: Vec<Oid> = row.proargtypes()?;
-
-        // actual arguments names may not be present, so fill them with "__argN" names
-        let argnames = match argnames {
-            Some(argnames) => argnames
-                .iter()
-                .enumerate()
-                .map(|(i, &name)| match name.is_empty() {
-                    true => Cow::Owned(format!("__arg{i}")),
-                    false => Cow::Borrowed(name),
-                })
-                .collect(),
-            None => argtypes.iter().enumerate().map(|(i, _)| Cow::Owned(format!("__arg{i}"))).collect(),
-        };
+    // types by oid, following domains to their base types and arrays to their elements
+    let mut type_oids: Vec<Oid> = Vec::new();
 
-        procs.push(Proc {
-            name: row.proname()?,
-            argnames,
-            argtypes,
-            comment: row.description()?,
-            rettype: 0,
-        });
+    for row in &columns_rows {
+        type_oids.push(row.oid()?);
     }
 
-    // get enum values and ignore the keys
-    let mut enums = enums.into_values().collect::<Vec<_>>();
+    for row in &procs_rows {
+        type_oids.extend(row.proargtypes::<Vec<Oid>>()?);
+    }
 
-    let schema_name = schema.map(|s| s.to_upper_camel_case());
+    for row in &ranges_rows {
+        type_oids.push(row.subtype()?);
+    }
 
-    let mut out_funcs = String::new();
-    let mut out_enums = String::new();
-    let mut out_tables = String::new();
+    for row in &composites_rows {
+        type_oids.push(row.atttypid()?);
+    }
 
-    // Funcs
-    {
-        let out = &mut out_funcs;
+    let mut types = HashMap::new();
+
+    while !type_oids.is_empty() {
+        #[rustfmt::skip]
+        let types_rows = client.query2(sql! {
+            const _: () = assert!(!Columns::IS_DYNAMIC);
+
+            SELECT
+                PgType.Oid AS @Oid,
+                PgType.Typname AS @Typname,
+                PgNamespace.Nspname AS @Nspname,
+                PgType.Typbasetype AS @Typbasetype,
+                PgType.Typelem AS @Typelem,
+                PgType.Typarray AS @Typarray
+            FROM PgType INNER JOIN PgNamespace ON PgNamespace.Oid = PgType.Typnamespace
+            WHERE PgType.Oid = ANY(#{&type_oids as Type::OID_ARRAY})
+        }).await?;
+
+        type_oids.clear();
+
+        for row in &types_rows {
+            let oid: Oid = row.oid()?;
+            let name: &str = row.typname()?;
+            let schema: &str = row.nspname()?;
+
+            let info = TypeInfo {
+                ty: TypeRef::new(oid, schema, name),
+                basetype: row.typbasetype()?,
+                elem: row.typelem()?,
+                array: row.typarray()?,
+            };
+
+            type_oids.extend([info.basetype, info.elem, info.array]);
+            types.insert(oid, info);
+        }
 
-        procs.sort_by_key(|f| f.name);
+        type_oids.retain(|oid| *oid != 0 && !types.contains_key(oid));
+        type_oids.sort_unstable();
+        type_oids.dedup();
+    }
 
-        out.push_str("thorn::functions! {\n");
+    let type_ref = |oid: Oid| resolve_type(&types, oid);
 
-        for proc in procs {
-            if let Some(comment) = proc.comment {
-                for line in textwrap::wrap(comment, COMMENT_WIDTH) {
-                    writeln!(out, "    /// {line}")?;
-                }
-            }
+    let mut model = SchemaModel::default();
 
-            write!(out, "    pub extern \"pg\" fn {}(", proc.name)?;
-
-            for (idx, (arg, &ty)) in proc.argnames.iter().zip(&proc.argtypes).enumerate() {
-                let ty = match Type::from_oid(ty) {
-                    Some(ty) => {
-                        uses_type = true;
-
-                        Some(PType(ty).to_string())
-                    }
-                    None => match enums.iter().find(|e| e.oid == ty) {
-                        Some(enum_) => Some(format!("{}.clone()", enum_.name.to_shouty_snake_case())),
-                        None => {
-                            eprintln!("Warning: Cannot find type: '{}' for '{}.{}'", ty, proc.name, arg);
-                            None
-                        }
-                    },
-                };
+    for schema in &schemas {
+        model.schema_mut(schema);
+    }
 
-                match ty {
-                    Some(ty) => write!(out, "{}: {}", arg, ty)?,
-                    None => write!(out, "{}", arg)?,
-                }
+    let mut tables: HashMap<(&str, &str), Table> = HashMap::new();
 
-                if (idx + 1) < proc.argnames.len() {
-                    out.push_str(", ");
-                }
-            }
+    for row in &columns_rows {
+        let table_schema: &str = row.table_schema()?;
+        let table_name: &str = row.table_name()?;
+        let table_comment: Option<&str> = row.table_comment()?;
+        let col_comment: Option<&str> = row.col_comment()?;
 
-            match schema_name {
-                Some(ref schema_name) => writeln!(out, ") in {schema_name};")?,
-                None => writeln!(out, ");")?,
-            }
-        }
+        let table = tables.entry((table_schema, table_name)).or_insert_with(|| Table {
+            name: table_name.to_owned(),
+            comment: table_comment.map(str::to_owned),
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            foreign_keys: Vec::new(),
+        });
 
-        out.push_str("}\n\n");
+        table.columns.push(Column {
+            name: row.column_name::<&str>()?.to_owned(),
+            ty: type_ref(row.oid()?),
+            nullable: row.nullable()?,
+            comment: col_comment.map(str::to_owned),
+        });
     }
 
-    // Enums
-    {
-        let out = &mut out_enums;
-
-        enums.sort_by_key(|e| e.name);
+    for row in &constraints_rows {
+        let schema: &str = row.nspname()?;
+        let table: &str = row.relname()?;
+        let contype: &str = row.contype()?;
 
-        let mut lazy_statics = String::new();
+        let Some(table) = tables.get_mut(&(schema, table)) else {
+            continue;
+        };
 
-        out.push_str("thorn::enums! {\n");
+        let column_names = |relid: Oid, keys: Vec<i16>| -> Vec<String> {
+            keys.iter().filter_map(|&num| attributes.get(&(relid, num))).map(|&name| name.to_owned()).collect()
+        };
 
-        for enum_ in &mut enums {
-            if let Some(comment) = enum_.comment {
-                for line in textwrap::wrap(comment, COMMENT_WIDTH) {
-                    writeln!(out, "    /// {line}")?;
-                }
+        match contype {
+            "p" => table.primary_key = column_names(row.conrelid()?, row.conkey()?),
+            "f" => {
+                let foreign_schema: &str = row.foreign_nspname()?;
+                let foreign_table: &str = row.foreign_relname()?;
+
+                table.foreign_keys.push(ForeignKey {
+                    name: row.conname::<&str>()?.to_owned(),
+                    columns: column_names(row.conrelid()?, row.conkey()?),
+                    foreign_schema: foreign_schema.to_owned(),
+                    foreign_table: foreign_table.to_owned(),
+                    foreign_columns: column_names(row.confrelid()?, row.confkey()?),
+                });
             }
+            _ => {}
+        }
+    }
 
-            let enum_name = enum_.name.to_upper_camel_case();
-
-            uses_type = true;
-
-            writeln!(
-                lazy_statics,
-                "/// See [{enum_name}] for full documentation\npub static {}: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| <{enum_name} as thorn::EnumType>::ty({}));\n",
-                enum_.name.to_shouty_snake_case(),
-                enum_.oid
-            )?;
+    for ((schema, _), table) in tables {
+        model.schema_mut(schema).tables.push(table);
+    }
 
-            // This isn't strictly necessary, but it's kind of a pointless rule for SQL enums
-            out.push_str("    #[allow(clippy::enum_variant_names)]\n");
+    let mut enums: HashMap<Oid, (&str, Enum)> = HashMap::new();
 
-            match schema_name {
-                Some(ref name) => writeln!(out, "    pub enum {enum_name} in {name} {{")?,
-                None => writeln!(out, "    pub enum {enum_name} {{")?,
-            }
+    for row in &enums_rows {
+        let oid: Oid = row.oid()?;
+        let array_oid: Oid = row.typarray()?;
+        let enum_name: &str = row.typname()?;
+        let enum_schema: &str = row.nspname()?;
+        let enum_comment: Option<&str> = row.description()?;
 
-            enum_.variants.sort_by(|a, b| a.position.total_cmp(&b.position));
+        let (_, enum_) = enums.entry(oid).or_insert_with(|| {
+            let enum_ = Enum {
+                oid,
+                array_oid,
+                name: enum_name.to_owned(),
+                comment: enum_comment.map(str::to_owned),
+                variants: Vec::new(),
+            };
 
-            for variant in &enum_.variants {
-                let variant_name = variant.name.to_upper_camel_case();
+            (enum_schema, enum_)
+        });
 
-                writeln!(out, "        {variant_name},")?;
-            }
+        enum_.variants.push(row.enumlabel::<&str>()?.to_owned());
+    }
 
-            out.push_str("    }\n");
-        }
+    for (schema, enum_) in enums.into_values() {
+        model.schema_mut(schema).enums.push(enum_);
+    }
 
-        out.push_str("}\n\n");
-        out.push_str(&lazy_statics);
+    for row in &ranges_rows {
+        let schema: &str = row.nspname()?;
+        let description: Option<&str> = row.description()?;
+
+        model.schema_mut(schema).ranges.push(RangeType {
+            oid: row.oid()?,
+            array_oid: row.typarray()?,
+            name: row.typname::<&str>()?.to_owned(),
+            comment: description.map(str::to_owned),
+            subtype: type_ref(row.subtype()?),
+            multirange_oid: row.multi_oid()?,
+            multirange_array_oid: row.multi_typarray()?,
+            multirange_name: row.multi_typname::<&str>()?.to_owned(),
+        });
     }
 
-    // Tables
-    {
-        let out = &mut out_tables;
+    let mut composites: HashMap<Oid, (&str, Composite)> = HashMap::new();
 
-        out.push_str("thorn::tables! {\n");
-        let mut tables = tables.into_values().collect::<Vec<_>>();
-        tables.sort_by_key(|t| t.name);
+    for row in &composites_rows {
+        let oid: Oid = row.oid()?;
+        let array_oid: Oid = row.typarray()?;
+        let composite_name: &str = row.typname()?;
+        let composite_schema: &str = row.nspname()?;
+        let composite_comment: Option<&str> = row.description()?;
+
+        let (_, composite) = composites.entry(oid).or_insert_with(|| {
+            let composite = Composite {
+                oid,
+                array_oid,
+                name: composite_name.to_owned(),
+                comment: composite_comment.map(str::to_owned),
+                fields: Vec::new(),
+            };
+
+            (composite_schema, composite)
+        });
 
-        for mut table in tables {
-            if let Some(comment) = table.comment {
-                for line in textwrap::wrap(comment, COMMENT_WIDTH) {
-                    writeln!(out, "    /// {line}")?;
-                }
-            }
+        composite.fields.push(Field {
+            name: row.attname::<&str>()?.to_owned(),
+            ty: type_ref(row.atttypid()?),
+        });
+    }
 
-            let table_name = table.name.to_upper_camel_case();
-            match schema_name {
-                Some(ref name) => writeln!(out, "    pub struct {table_name} in {name} {{")?,
-                None => writeln!(out, "    pub struct {table_name} {{")?,
-            }
+    for (schema, composite) in composites.into_values() {
+        model.schema_mut(schema).composites.push(composite);
+    }
 
-            table.cols.sort_by_key(|c| c.position);
-
-            for col in &table.cols {
-                let ty = match Type::from_oid(col.ty) {
-                    Some(ty) => {
-                        uses_type = true;
-                        PType(ty).to_string()
-                    }
-                    None => match enums.iter().find(|e| e.oid == col.ty) {
-                        Some(enum_) => format!("{}.clone()", enum_.name.to_shouty_snake_case()),
-                        None => {
-                            eprintln!(
-                                "Warning: Cannot find type: '{}' for '{}.{}'",
-                                col.udt, table.name, col.name
-                            );
-                            continue;
-                        }
-                    },
-                };
+    for row in &procs_rows {
+        let name: &str = row.proname()?;
+        let schema = model.schema_mut(row.nspname()?);
 
-                let column_name = col.name.to_upper_camel_case();
+        // skip the constructor functions created along with range types
+        if schema.ranges.iter().any(|r| r.name == name || r.multirange_name == name) {
+            continue;
+        }
 
-                if let Some(comment) = col.comment {
-                    for line in textwrap::wrap(comment, 66) {
-                        writeln!(out, "        /// {line}")?;
-                    }
-                }
+        let argnames: Option<Vec<&str>> = row.proargnames()?;
+        let argtypes: Vec<Oid> = row.proargtypes()?;
 
-                if col.null {
-                    uses_nullable = true;
+        let args = argtypes
+            .iter()
+            .enumerate()
+            .map(|(i, &ty)| {
+                // actual arguments names may not be present, so fill them with "__argN" names
+                let name = match argnames.as_ref().and_then(|names| names.get(i)) {
+                    Some(name) if !name.is_empty() => Cow::Borrowed(*name),
+                    _ => Cow::Owned(format!("__arg{i}")),
+                };
 
-                    writeln!(out, "        {column_name}: Nullable({}),", ty)?;
-                } else {
-                    writeln!(out, "        {column_name}: {},", ty)?;
+                Argument {
+                    name: name.into_owned(),
+                    ty: type_ref(ty),
                 }
-            }
+            })
+            .collect();
 
-            out.push_str("    }\n\n");
-        }
+        let description: Option<&str> = row.description()?;
 
-        out.push_str("}\n");
+        schema.functions.push(Function {
+            name: name.to_owned(),
+            comment: description.map(str::to_owned),
+            args,
+        });
     }
 
-    let mut out = String::with_capacity(out_funcs.len() + out_enums.len() + out_tables.len() + 256);
+    model.sort();
 
-    out.push_str("#![rustfmt::skip]\n");
+    Ok(model)
+}
 
-    if uses_nullable {
-        out.push_str("use thorn::table::Nullable;\n\n");
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emitters() {
+        let model = SchemaModel {
+            schemas: vec![Schema {
+                name: "app".to_owned(),
+                tables: vec![Table {
+                    name: "users".to_owned(),
+                    comment: Some("Registered users".to_owned()),
+                    columns: vec![
+                        Column {
+                            name: "id".to_owned(),
+                            ty: TypeRef::new(20, "pg_catalog", "int8"),
+                            nullable: false,
+                            comment: None,
+                        },
+                        Column {
+                            name: "mood".to_owned(),
+                            ty: TypeRef::new(16400, "app", "mood"),
+                            nullable: true,
+                            comment: Some("Current | mood".to_owned()),
+                        },
+                        Column {
+                            name: "past_moods".to_owned(),
+                            ty: TypeRef::new(16401, "app", "_mood"),
+                            nullable: false,
+                            comment: None,
+                        },
+                    ],
+                    primary_key: vec!["id".to_owned()],
+                    foreign_keys: Vec::new(),
+                }],
+                enums: vec![Enum {
+                    oid: 16400,
+                    array_oid: 16401,
+                    name: "mood".to_owned(),
+                    comment: None,
+                    variants: vec!["happy".to_owned(), "sad".to_owned()],
+                }],
+                composites: vec![Composite {
+                    oid: 16410,
+                    array_oid: 16411,
+                    name: "status".to_owned(),
+                    comment: None,
+                    fields: vec![
+                        Field {
+                            name: "note".to_owned(),
+                            ty: TypeRef::new(25, "pg_catalog", "text"),
+                        },
+                        Field {
+                            name: "mood".to_owned(),
+                            ty: TypeRef::new(16400, "app", "mood"),
+                        },
+                    ],
+                }],
+                ..Schema::default()
+            }],
+        };
 
-    if uses_type {
-        out.push_str("use thorn::pg::Type;\n\n");
+        let rust = RustEmitter::default().emit(&model).unwrap();
+
+        assert!(
+            rust.contains("    pub struct Users in App {\n        Id: Type::INT8,\n"),
+            "{rust}"
+        );
+        assert!(rust.contains("        Mood: Nullable(MOOD.clone()),\n"), "{rust}");
+        assert!(rust.contains("<Mood as thorn::EnumType>::ty(16400)"), "{rust}");
+        assert!(rust.contains("        PastMoods: MOOD_ARRAY.clone(),\n"), "{rust}");
+        assert!(
+            rust.contains(concat!(
+                "pub static MOOD_ARRAY: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| ",
+                r#"Type::new("_mood".to_owned(), 16401, thorn::pg::Kind::Array(MOOD.clone()), "app".to_owned()));"#,
+            )),
+            "{rust}"
+        );
+        assert!(rust.contains("pub static STATUS_ARRAY: "), "{rust}");
+        assert!(
+            rust.contains(concat!(
+                "pub static STATUS: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| ",
+                r#"Type::new("status".to_owned(), 16410, thorn::pg::Kind::Composite(vec!["#,
+                r#"thorn::pg::Field::new("note".to_owned(), Type::TEXT), "#,
+                r#"thorn::pg::Field::new("mood".to_owned(), MOOD.clone())]), "app".to_owned()));"#,
+            )),
+            "{rust}"
+        );
+
+        let json = JsonEmitter.emit(&model).unwrap();
+
+        assert_eq!(serde_json::from_str::<SchemaModel>(&json).unwrap(), model);
+
+        let md = MarkdownEmitter.emit(&model).unwrap();
+
+        assert!(md.contains("| `id` (PK) | `int8` | no |  |\n"), "{md}");
+        assert!(md.contains("| `mood` | `mood` | yes | Current \\| mood |\n"), "{md}");
+        assert!(md.contains("| `past_moods` | `mood[]` | no |  |\n"), "{md}");
+        assert!(
+            md.contains("### `status`\n\n| Field | Type |\n|-------|------|\n| `note` | `text` |\n"),
+            "{md}"
+        );
+
+        let mut model = model;
+        model.schemas[0].tables[0].columns[0].ty = TypeRef::new(16500, "app", "citext");
+
+        let err = RustEmitter::default().emit(&model).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot find type \"app.citext\" for column \"app.users.id\", it can be mapped in the `types` config"
+        );
+
+        let config = Config::from_toml("[types]\ncitext = \"Type::TEXT\"\n").unwrap();
+        let rust = RustEmitter::new(config).emit(&model).unwrap();
+
+        assert!(rust.contains("        Id: Type::TEXT,\n"), "{rust}");
     }
 
-    out += &out_funcs;
-    out += &out_enums;
-    out += &out_tables;
-
-    Ok(out)
-}
-
-struct PType(pub Type);
-
-impl fmt::Display for PType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Type::{}", format!("{:?}", self.0).to_shouty_snake_case())
+    /// Set `THORN_TEST_DB` to a connection string to run
+    #[tokio::test]
+    #[ignore]
+    async fn test_colliding_type_names() {
+        let db = std::env::var("THORN_TEST_DB").expect("THORN_TEST_DB is not set");
+        let (client, conn) = pgt::connect(&db, pgt::NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        let fixture = r#"
+            DROP SCHEMA IF EXISTS thorn_gen_a, thorn_gen_b CASCADE;
+            CREATE SCHEMA thorn_gen_a;
+            CREATE SCHEMA thorn_gen_b;
+            CREATE TYPE thorn_gen_a.mood AS ENUM ('happy', 'sad');
+            CREATE TYPE thorn_gen_b.mood AS ENUM ('calm', 'angry', 'bored');
+            CREATE TYPE thorn_gen_b.pair AS (mood thorn_gen_a.mood, other thorn_gen_b.mood);
+            CREATE TABLE thorn_gen_a.people (
+                id int8 NOT NULL, mood thorn_gen_a.mood NOT NULL, other thorn_gen_b.mood, pair thorn_gen_b.pair,
+                moods thorn_gen_b.mood[] NOT NULL
+            );
+            CREATE TABLE thorn_gen_b.people (mood thorn_gen_b.mood NOT NULL);
+        "#;
+
+        client.batch_execute(fixture).await.unwrap();
+
+        let schemas = ["thorn_gen_a".to_owned(), "thorn_gen_b".to_owned()];
+        let res = generate(&client, &schemas, &Config::default()).await;
+
+        client.batch_execute("DROP SCHEMA thorn_gen_a, thorn_gen_b CASCADE").await.unwrap();
+
+        let out = res.unwrap();
+
+        let (a, b) = out.split_once("pub mod thorn_gen_b {").unwrap();
+
+        let people_a = "pub struct People in ThornGenA {
+            Id: Type::INT8,
+            Mood: MOOD.clone(),
+            Other: Nullable(super::thorn_gen_b::MOOD.clone()),
+            Pair: Nullable(super::thorn_gen_b::PAIR.clone()),
+            Moods: super::thorn_gen_b::MOOD_ARRAY.clone(),
+        }";
+
+        let people_b = "pub struct People in ThornGenB {
+            Mood: MOOD.clone(),
+        }";
+
+        assert!(a.contains(people_a), "{out}");
+        assert!(a.contains("Happy,\n") && !a.contains("Calm,\n"), "{out}");
+
+        assert!(b.contains(people_b), "{out}");
+        assert!(b.contains("Calm,\n") && !b.contains("Happy,\n"), "{out}");
+        assert!(
+            b.contains(concat!(
+                r#"thorn::pg::Kind::Composite(vec![thorn::pg::Field::new("mood".to_owned(), super::thorn_gen_a::MOOD.clone()), "#,
+                r#"thorn::pg::Field::new("other".to_owned(), MOOD.clone())]), "thorn_gen_b".to_owned())"#,
+            )),
+            "{out}"
+        );
     }
 }
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Error, TypeRef};
+
+/// Generator configuration, usually read from a TOML file:
+///
+/// ```toml
+/// [types]
+/// citext = "Type::TEXT"
+/// "public.ltree" = "crate::types::LTREE.clone()"
+/// 16522 = "crate::types::GEOMETRY.clone()"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Rust expressions for the `pg::Type` of a type, by oid, `schema.name` or name.
+    ///
+    /// These take precedence over builtin and generated types.
+    pub types: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Find the override for `ty`, by oid, then by qualified name, then by name
+    pub fn type_override(&self, ty: &TypeRef) -> Option<&str> {
+        let by_oid = match ty.oid {
+            0 => None,
+            oid => self.types.get(&oid.to_string()),
+        };
+
+        by_oid
+            .or_else(|| self.types.get(&format!("{}.{}", ty.schema, ty.name)))
+            .or_else(|| self.types.get(&ty.name))
+            .map(String::as_str)
+    }
+}
@@ -0,0 +1,1388 @@
+//! Offline schema ingestion from SQL DDL, such as `pg_dump --schema-only` output or migration files.
+//!
+//! Supported statements are `CREATE TABLE` (including typed tables and partitions), `CREATE TYPE ... AS ENUM`,
+//! `CREATE TYPE ... AS RANGE`, `CREATE TYPE ... AS (...)`, `CREATE DOMAIN`, `CREATE FUNCTION`, `COMMENT ON`
+//! (tables, columns, types and functions), `ALTER TABLE ... ADD` (columns and constraints),
+//! `ALTER TABLE ... ATTACH PARTITION`, `CREATE SCHEMA` and `SET search_path`. Any other statement is skipped,
+//! as are tables created with `AS SELECT`.
+//!
+//! Builtin types are resolved by name to their oids, while user-defined types have an oid of `0`.
+//! Domains are resolved to their base types.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use pg::Type;
+
+use super::{
+    Argument, Column, Composite, Enum, Error, Field, ForeignKey, Function, RangeType, SchemaModel, Table, TypeRef,
+};
+use crate::lexer;
+
+/// Builtin types by name, as known by `postgres-types`
+static BUILTINS: LazyLock<HashMap<String, Type>> =
+    LazyLock::new(|| (0..16384).filter_map(Type::from_oid).map(|ty| (ty.name().to_owned(), ty)).collect());
+
+/// Parse SQL DDL statements into a [`SchemaModel`], with unqualified names in the `public` schema
+/// unless changed by `SET search_path`.
+pub fn parse_ddl(sql: &str) -> Result<SchemaModel, Error> {
+    let tokens: Vec<_> =
+        lexer::tokens(sql).filter(|(_, t)| !t.starts_with("--") && !t.starts_with("/*")).collect();
+
+    let mut ddl = Ddl {
+        search_path: "public".to_owned(),
+        model: SchemaModel::default(),
+        domains: HashMap::new(),
+        partitions: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for stmt in tokens.split(|&(_, t)| t == ";") {
+        if stmt.is_empty() {
+            continue;
+        }
+
+        ddl.statement(Cursor {
+            sql,
+            toks: stmt,
+            pos: 0,
+        })?;
+    }
+
+    ddl.resolve_foreign_keys();
+    ddl.model.sort();
+
+    Ok(ddl.model)
+}
+
+struct Ddl {
+    search_path: String,
+    model: SchemaModel,
+
+    /// Base types of domains, by schema and name
+    domains: HashMap<(String, String), TypeRef>,
+
+    /// `(parent, partition)` pairs of partitioned tables, by schema and name
+    partitions: Vec<((String, String), (String, String))>,
+
+    /// Tables created without columns of their own, such as with `AS SELECT`
+    skipped: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+enum Constraint {
+    PrimaryKey(Vec<String>),
+    ForeignKey(ForeignKey),
+}
+
+impl Ddl {
+    fn statement(&mut self, mut c: Cursor) -> Result<(), Error> {
+        if c.eat_kw("create") {
+            c.eat_kws(&["or", "replace"]);
+
+            if c.eat_kw("schema") {
+                c.eat_kws(&["if", "not", "exists"]);
+                let name = c.ident()?;
+                self.model.schema_mut(&name);
+            } else if c.eat_kw("type") {
+                self.create_type(c)?;
+            } else if c.eat_kw("domain") {
+                let name = self.qualified(&mut c)?;
+                c.eat_kw("as");
+
+                // domains of domains resolve to the innermost base type
+                let (base, _) = self.ty(&mut c)?;
+                self.domains.insert(name, base);
+            } else if c.eat_kw("function") {
+                self.create_function(c)?;
+            } else {
+                // CREATE [GLOBAL | LOCAL] [TEMPORARY | TEMP | UNLOGGED] TABLE
+                while c.eat_kw("global") || c.eat_kw("local") || c.eat_kw("temporary") || c.eat_kw("temp") {}
+                c.eat_kw("unlogged");
+
+                if c.eat_kw("table") {
+                    self.create_table(c)?;
+                }
+            }
+        } else if c.eat_kws(&["alter", "table"]) {
+            self.alter_table(c)?;
+        } else if c.eat_kws(&["comment", "on"]) {
+            self.comment_on(c)?;
+        } else if c.eat_kws(&["set", "search_path"]) {
+            if !c.eat_kw("to") {
+                c.expect("=")?;
+            }
+
+            // an empty search path, such as `''`, leaves it unchanged
+            if let Ok(schema) = c.ident() {
+                self.search_path = schema;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn qualified(&self, c: &mut Cursor) -> Result<(String, String), Error> {
+        let (schema, name) = c.qualified()?;
+        Ok((schema.unwrap_or_else(|| self.search_path.clone()), name))
+    }
+
+    fn table_mut(&mut self, c: &Cursor, schema: &str, name: &str) -> Result<&mut Table, Error> {
+        let schema = self.model.schema_mut(schema);
+
+        match schema.tables.iter_mut().find(|t| t.name == name) {
+            Some(table) => Ok(table),
+            None => Err(c.error(format!("unknown table \"{}.{name}\"", schema.name))),
+        }
+    }
+
+    fn create_table(&mut self, mut c: Cursor) -> Result<(), Error> {
+        c.eat_kws(&["if", "not", "exists"]);
+
+        let (schema, name) = self.qualified(&mut c)?;
+
+        let mut table = Table {
+            name,
+            comment: None,
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            foreign_keys: Vec::new(),
+        };
+
+        // typed tables and partitions take their columns from a composite type or their parent,
+        // and may only add constraints to them
+        let mut parent = None;
+        let copied = match () {
+            _ if c.eat_kws(&["partition", "of"]) => {
+                let (parent_schema, parent_name) = self.qualified(&mut c)?;
+                let parent_table = self.table_mut(&c, &parent_schema, &parent_name)?;
+
+                table.columns = parent_table
+                    .columns
+                    .iter()
+                    .map(|col| Column {
+                        comment: None,
+                        ..col.clone()
+                    })
+                    .collect();
+                parent = Some((parent_schema, parent_name));
+
+                true
+            }
+            _ if c.eat_kw("of") => {
+                let (type_schema, type_name) = self.qualified(&mut c)?;
+
+                let Some(composite) = self
+                    .model
+                    .schema(&type_schema)
+                    .and_then(|s| s.composites.iter().find(|t| t.name == type_name))
+                else {
+                    return Err(c.error(format!("unknown composite type \"{type_schema}.{type_name}\"")));
+                };
+
+                table.columns = composite
+                    .fields
+                    .iter()
+                    .map(|field| Column {
+                        name: field.name.clone(),
+                        ty: field.ty.clone(),
+                        nullable: true,
+                        comment: None,
+                    })
+                    .collect();
+
+                true
+            }
+            // AS SELECT and AS EXECUTE
+            _ if c.peek() != Some("(") => {
+                self.skipped.push((schema, table.name));
+                return Ok(());
+            }
+            _ => false,
+        };
+
+        let mut constraints = Vec::new();
+
+        if c.peek() == Some("(") {
+            for mut element in c.group()?.split_commas() {
+                if element.peek().is_none() || element.eat_kw("like") {
+                    continue;
+                }
+
+                match self.table_constraint(&mut element, &table.name)? {
+                    Some(constraint) => constraints.push(constraint),
+                    None if element.is_constraint() => {}
+                    // `column [WITH OPTIONS] constraints` of a typed table or partition
+                    None if copied => {
+                        let name = element.ident()?;
+                        element.eat_kws(&["with", "options"]);
+
+                        let (not_null, column_constraints) =
+                            self.column_constraints(&mut element, &table.name, &name)?;
+
+                        match table.columns.iter_mut().find(|col| col.name == name) {
+                            Some(col) => col.nullable &= !not_null,
+                            None => return Err(element.error(format!("unknown column \"{name}\""))),
+                        }
+
+                        constraints.extend(column_constraints);
+                    }
+                    None => {
+                        let (column, column_constraints) = self.column(&mut element, &table.name)?;
+
+                        table.columns.push(column);
+                        constraints.extend(column_constraints);
+                    }
+                }
+            }
+        }
+
+        for constraint in constraints {
+            apply(&mut table, constraint);
+        }
+
+        let child = (schema.clone(), table.name.clone());
+        self.model.schema_mut(&schema).tables.push(table);
+
+        if let Some(parent) = parent {
+            self.attach(&c, parent, child)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record `child` as a partition of `parent`, which inherits its primary and foreign keys
+    fn attach(&mut self, c: &Cursor, parent: (String, String), child: (String, String)) -> Result<(), Error> {
+        let parent_table = self.table_mut(c, &parent.0, &parent.1)?;
+        let (primary_key, foreign_keys) = (parent_table.primary_key.clone(), parent_table.foreign_keys.clone());
+
+        let table = self.table_mut(c, &child.0, &child.1)?;
+
+        if table.primary_key.is_empty() && !primary_key.is_empty() {
+            apply(table, Constraint::PrimaryKey(primary_key));
+        }
+
+        for fk in foreign_keys {
+            if !table.foreign_keys.iter().any(|f| f.name == fk.name) {
+                table.foreign_keys.push(fk);
+            }
+        }
+
+        self.partitions.push((parent, child));
+
+        Ok(())
+    }
+
+    /// Parse a column definition of `table`, along with its inline constraints
+    fn column(&self, c: &mut Cursor, table: &str) -> Result<(Column, Vec<Constraint>), Error> {
+        let name = c.ident()?;
+        let (ty, serial) = self.ty(c)?;
+
+        let (not_null, constraints) = self.column_constraints(c, table, &name)?;
+
+        let column = Column {
+            name,
+            ty,
+            nullable: !serial && !not_null,
+            comment: None,
+        };
+
+        Ok((column, constraints))
+    }
+
+    /// Parse the inline constraints of column `name`, returning whether it is `NOT NULL`
+    fn column_constraints(
+        &self,
+        c: &mut Cursor,
+        table: &str,
+        name: &str,
+    ) -> Result<(bool, Vec<Constraint>), Error> {
+        let mut not_null = false;
+        let mut constraints = Vec::new();
+        let mut constraint_name = None;
+
+        while let Some(token) = c.peek() {
+            if token == "(" {
+                c.group()?;
+            } else if c.eat_kw("constraint") {
+                constraint_name = Some(c.ident()?);
+            } else if c.eat_kws(&["not", "null"]) {
+                not_null = true;
+            } else if c.eat_kws(&["primary", "key"]) {
+                constraints.push(Constraint::PrimaryKey(vec![name.to_owned()]));
+            } else if c.eat_kw("references") {
+                let fk_name = constraint_name.take().unwrap_or_else(|| format!("{table}_{name}_fkey"));
+                let fk = self.references(c, fk_name, vec![name.to_owned()])?;
+                constraints.push(Constraint::ForeignKey(fk));
+            } else {
+                c.next();
+            }
+        }
+
+        Ok((not_null, constraints))
+    }
+
+    /// Parse a table constraint, returning `None` for unsupported or non-constraint elements
+    fn table_constraint(&self, c: &mut Cursor, table: &str) -> Result<Option<Constraint>, Error> {
+        let start = c.pos;
+
+        let name = match c.eat_kw("constraint") {
+            true => Some(c.ident()?),
+            false => None,
+        };
+
+        if c.eat_kws(&["primary", "key"]) {
+            return Ok(Some(Constraint::PrimaryKey(c.group()?.idents()?)));
+        }
+
+        if c.eat_kws(&["foreign", "key"]) {
+            let columns = c.group()?.idents()?;
+            c.expect_kw("references")?;
+
+            let name = name.unwrap_or_else(|| format!("{table}_{}_fkey", columns.join("_")));
+
+            return Ok(Some(Constraint::ForeignKey(self.references(c, name, columns)?)));
+        }
+
+        c.pos = start;
+
+        Ok(None)
+    }
+
+    /// Parse the target of `REFERENCES table [(columns)]`
+    fn references(&self, c: &mut Cursor, name: String, columns: Vec<String>) -> Result<ForeignKey, Error> {
+        let (foreign_schema, foreign_table) = self.qualified(c)?;
+
+        let foreign_columns = match c.peek() {
+            Some("(") => c.group()?.idents()?,
+            _ => Vec::new(),
+        };
+
+        Ok(ForeignKey {
+            name,
+            columns,
+            foreign_schema,
+            foreign_table,
+            foreign_columns,
+        })
+    }
+
+    /// Parse a type name, returning whether it was a `serial` pseudo-type
+    fn ty(&self, c: &mut Cursor) -> Result<(TypeRef, bool), Error> {
+        let (first, quoted) = c.ident_quoted()?;
+
+        let (schema, mut name, quoted) = match c.peek() {
+            Some(".") => {
+                c.next();
+                let (name, quoted) = c.ident_quoted()?;
+                (Some(first), name, quoted)
+            }
+            _ => (None, first, quoted),
+        };
+
+        let builtin = schema.as_deref().is_none_or(|s| s == "pg_catalog");
+
+        // multi-word types and type modifiers, such as `timestamp(3) with time zone`
+        loop {
+            match c.peek() {
+                Some("(") => {
+                    c.group()?;
+                }
+                Some(word) if builtin && !quoted && is_type_word(word) => {
+                    name.push(' ');
+                    name.push_str(&word.to_ascii_lowercase());
+                    c.next();
+                }
+                _ => break,
+            }
+        }
+
+        let mut array = false;
+
+        loop {
+            if c.eat("[") {
+                while c.next().is_some_and(|t| t != "]") {}
+                array = true;
+            } else if c.eat_kw("array") {
+                array = true;
+            } else {
+                break;
+            }
+        }
+
+        let serial = builtin
+            && !quoted
+            && matches!(
+                &*name,
+                "serial" | "serial4" | "bigserial" | "serial8" | "smallserial" | "serial2"
+            );
+
+        if builtin && !quoted {
+            name = alias(&name).to_owned();
+        }
+
+        let schema = match schema {
+            None if BUILTINS.contains_key(&name) => Some("pg_catalog".to_owned()),
+            schema => schema.or_else(|| Some(self.search_path.clone())),
+        };
+
+        if let Some(base) = self.domains.get(&(schema.clone().unwrap_or_default(), name.clone())) {
+            return Ok((if array { array_of(base) } else { base.clone() }, false));
+        }
+
+        if array {
+            name.insert(0, '_');
+        }
+
+        if builtin {
+            if let Some(ty) = BUILTINS.get(&name) {
+                return Ok((TypeRef::new(ty.oid(), "pg_catalog", name), serial));
+            }
+        }
+
+        Ok((TypeRef::new(0, schema.unwrap_or_default(), name), serial))
+    }
+
+    fn create_type(&mut self, mut c: Cursor) -> Result<(), Error> {
+        let (schema, name) = self.qualified(&mut c)?;
+
+        if !c.eat_kw("as") {
+            return Ok(());
+        }
+
+        if c.peek() == Some("(") {
+            let mut fields = Vec::new();
+
+            for mut field in c.group()?.split_commas() {
+                if field.peek().is_some() {
+                    let name = field.ident()?;
+
+                    fields.push(Field {
+                        name,
+                        ty: self.ty(&mut field)?.0,
+                    });
+                }
+            }
+
+            self.model.schema_mut(&schema).composites.push(Composite {
+                oid: 0,
+                array_oid: 0,
+                name,
+                comment: None,
+                fields,
+            });
+        } else if c.eat_kw("enum") {
+            let mut variants = Vec::new();
+
+            for mut label in c.group()?.split_commas() {
+                if label.peek().is_some() {
+                    variants.push(label.string()?);
+                }
+            }
+
+            self.model.schema_mut(&schema).enums.push(Enum {
+                oid: 0,
+                array_oid: 0,
+                name,
+                comment: None,
+                variants,
+            });
+        } else if c.eat_kw("range") {
+            let mut subtype = None;
+
+            // see https://www.postgresql.org/docs/current/sql-createtype.html#SQL-CREATETYPE-RANGE
+            let mut multirange_name = match name.find("range") {
+                Some(idx) => format!("{}multirange{}", &name[..idx], &name[idx + 5..]),
+                None => format!("{name}_multirange"),
+            };
+
+            for mut option in c.group()?.split_commas() {
+                let key = option.ident()?;
+                option.expect("=")?;
+
+                match &*key {
+                    "subtype" => subtype = Some(self.ty(&mut option)?.0),
+                    "multirange_type_name" => multirange_name = option.qualified()?.1,
+                    _ => {}
+                }
+            }
+
+            let Some(subtype) = subtype else {
+                return Err(c.error(format!("range type \"{name}\" has no subtype")));
+            };
+
+            self.model.schema_mut(&schema).ranges.push(RangeType {
+                oid: 0,
+                array_oid: 0,
+                name,
+                comment: None,
+                subtype,
+                multirange_oid: 0,
+                multirange_array_oid: 0,
+                multirange_name,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn create_function(&mut self, mut c: Cursor) -> Result<(), Error> {
+        let (schema, name) = self.qualified(&mut c)?;
+
+        let mut args = Vec::new();
+
+        for (i, mut arg) in c.group()?.split_commas().into_iter().enumerate() {
+            if arg.peek().is_none() {
+                continue;
+            }
+
+            if arg.eat_kw("out") || arg.eat_kw("variadic") {
+                // output arguments are not part of the signature, and variadic functions are skipped
+                if arg.toks.first().is_some_and(|&(_, t)| t.eq_ignore_ascii_case("variadic")) {
+                    return Ok(());
+                }
+
+                continue;
+            }
+
+            let _ = arg.eat_kw("inout") || arg.eat_kw("in");
+
+            // `name type` rather than a lone type, such as `double precision` or `int[]`
+            let named = match (arg.peek(), arg.toks.get(arg.pos + 1)) {
+                (Some(first), Some(&(_, second))) => {
+                    let continues_type = matches!(second, "." | "(" | "[" | "=")
+                        || second.eq_ignore_ascii_case("default")
+                        || second.eq_ignore_ascii_case("array")
+                        || is_type_word(second) && is_type_start(first);
+
+                    !continues_type
+                }
+                _ => false,
+            };
+
+            let name = match named {
+                true => arg.ident()?,
+                false => format!("__arg{i}"),
+            };
+
+            args.push(Argument {
+                name,
+                ty: self.ty(&mut arg)?.0,
+            });
+        }
+
+        if c.eat_kw("returns") && c.eat_kw("trigger") {
+            return Ok(());
+        }
+
+        self.model.schema_mut(&schema).functions.push(Function {
+            name,
+            comment: None,
+            args,
+        });
+
+        Ok(())
+    }
+
+    fn comment_on(&mut self, mut c: Cursor) -> Result<(), Error> {
+        let kind = c.ident()?;
+
+        // object reference, up to `IS`
+        let mut target = c.clone();
+        while c.peek().is_some() && !c.peek_kw("is") {
+            if c.peek() == Some("(") {
+                c.group()?;
+            } else {
+                c.next();
+            }
+        }
+
+        c.expect_kw("is")?;
+
+        let comment = match c.eat_kw("null") {
+            true => None,
+            false => Some(c.string()?),
+        };
+
+        match &*kind {
+            "table" => {
+                let (schema, name) = self.qualified(&mut target)?;
+
+                if let Some(table) = self.model.schema_mut(&schema).tables.iter_mut().find(|t| t.name == name) {
+                    table.comment = comment;
+                }
+            }
+            "column" => {
+                let mut parts = vec![target.ident()?];
+
+                while target.eat(".") {
+                    parts.push(target.ident()?);
+                }
+
+                let (schema, table, column) = match &parts[..] {
+                    [table, column] => (self.search_path.clone(), table, column),
+                    [schema, table, column] => (schema.clone(), table, column),
+                    _ => return Err(target.error("expected table.column")),
+                };
+
+                let schema = self.model.schema_mut(&schema);
+
+                if let Some(col) = schema
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.name == *table)
+                    .and_then(|t| t.columns.iter_mut().find(|c| c.name == *column))
+                {
+                    col.comment = comment;
+                }
+            }
+            "type" => {
+                let (schema, name) = self.qualified(&mut target)?;
+                let schema = self.model.schema_mut(&schema);
+
+                if let Some(enum_) = schema.enums.iter_mut().find(|e| e.name == name) {
+                    enum_.comment = comment;
+                } else if let Some(range) = schema.ranges.iter_mut().find(|r| r.name == name) {
+                    range.comment = comment;
+                } else if let Some(composite) = schema.composites.iter_mut().find(|t| t.name == name) {
+                    composite.comment = comment;
+                }
+            }
+            "function" => {
+                let (schema, name) = self.qualified(&mut target)?;
+
+                let arity = match target.peek() {
+                    Some("(") => {
+                        Some(target.group()?.split_commas().iter().filter(|a| a.peek().is_some()).count())
+                    }
+                    _ => None,
+                };
+
+                let schema = self.model.schema_mut(&schema);
+
+                if let Some(func) =
+                    schema.functions.iter_mut().find(|f| f.name == name && arity.is_none_or(|n| n == f.args.len()))
+                {
+                    func.comment = comment;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn alter_table(&mut self, mut c: Cursor) -> Result<(), Error> {
+        c.eat_kws(&["if", "exists"]);
+        let only = c.eat_kw("only");
+
+        let (schema, name) = self.qualified(&mut c)?;
+        c.eat("*");
+
+        if self.skipped.contains(&(schema.clone(), name.clone())) {
+            return Ok(());
+        }
+
+        // without ONLY, additions to a partitioned table also apply to its partitions
+        let mut targets = vec![(schema.clone(), name.clone())];
+
+        let mut idx = 0;
+        while !only && idx < targets.len() {
+            let children = self.partitions.iter().filter(|(parent, _)| *parent == targets[idx]);
+            let children: Vec<_> = children.map(|(_, child)| child.clone()).collect();
+
+            targets.extend(children);
+            idx += 1;
+        }
+
+        for mut action in c.split_commas() {
+            if action.eat_kws(&["attach", "partition"]) {
+                let child = self.qualified(&mut action)?;
+                self.attach(&action, (schema.clone(), name.clone()), child)?;
+                continue;
+            }
+
+            if !action.eat_kw("add") {
+                continue;
+            }
+
+            let (column, constraints) = match self.table_constraint(&mut action, &name)? {
+                Some(constraint) => (None, vec![constraint]),
+                None if action.is_constraint() => continue,
+                None => {
+                    action.eat_kw("column");
+                    action.eat_kws(&["if", "not", "exists"]);
+
+                    let (column, constraints) = self.column(&mut action, &name)?;
+                    (Some(column), constraints)
+                }
+            };
+
+            for (schema, name) in &targets {
+                let table = self.table_mut(&action, schema, name)?;
+
+                table.columns.extend(column.clone());
+
+                for constraint in &constraints {
+                    apply(table, constraint.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Foreign keys without explicit columns reference the primary key
+    fn resolve_foreign_keys(&mut self) {
+        let primary_keys: HashMap<(String, String), Vec<String>> = self
+            .model
+            .schemas
+            .iter()
+            .flat_map(|s| s.tables.iter().map(|t| ((s.name.clone(), t.name.clone()), t.primary_key.clone())))
+            .collect();
+
+        for schema in &mut self.model.schemas {
+            for table in &mut schema.tables {
+                for fk in &mut table.foreign_keys {
+                    if fk.foreign_columns.is_empty() {
+                        if let Some(pk) = primary_keys.get(&(fk.foreign_schema.clone(), fk.foreign_table.clone()))
+                        {
+                            fk.foreign_columns = pk.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Array type of `elem`
+fn array_of(elem: &TypeRef) -> TypeRef {
+    let name = format!("_{}", elem.name);
+
+    let oid = match &*elem.schema {
+        "pg_catalog" => BUILTINS.get(&name).map_or(0, Type::oid),
+        _ => 0,
+    };
+
+    TypeRef::new(oid, elem.schema.clone(), name)
+}
+
+fn apply(table: &mut Table, constraint: Constraint) {
+    match constraint {
+        Constraint::PrimaryKey(columns) => {
+            for col in &mut table.columns {
+                if columns.contains(&col.name) {
+                    col.nullable = false;
+                }
+            }
+
+            table.primary_key = columns;
+        }
+        Constraint::ForeignKey(fk) => table.foreign_keys.push(fk),
+    }
+}
+
+/// Words that continue a multi-word builtin type name
+fn is_type_word(word: &str) -> bool {
+    ["precision", "varying", "with", "without", "time", "zone"]
+        .iter()
+        .any(|w| word.eq_ignore_ascii_case(w))
+}
+
+/// First words of multi-word builtin type names
+fn is_type_start(word: &str) -> bool {
+    ["double", "character", "char", "bit", "timestamp", "time"]
+        .iter()
+        .any(|w| word.eq_ignore_ascii_case(w))
+}
+
+/// Internal name of builtin type aliases
+fn alias(name: &str) -> &str {
+    match name {
+        "int" | "integer" | "serial" | "serial4" => "int4",
+        "smallint" | "smallserial" | "serial2" => "int2",
+        "bigint" | "bigserial" | "serial8" => "int8",
+        "boolean" => "bool",
+        "real" => "float4",
+        "float" | "double precision" => "float8",
+        "decimal" | "dec" => "numeric",
+        "char" | "character" => "bpchar",
+        "character varying" | "char varying" => "varchar",
+        "bit varying" => "varbit",
+        "timestamp without time zone" => "timestamp",
+        "timestamp with time zone" => "timestamptz",
+        "time without time zone" => "time",
+        "time with time zone" => "timetz",
+        name => name,
+    }
+}
+
+/// Token cursor over a single statement, or a part of one
+#[derive(Clone)]
+struct Cursor<'a, 's> {
+    sql: &'a str,
+    toks: &'s [(usize, &'a str)],
+    pos: usize,
+}
+
+impl<'a, 's> Cursor<'a, 's> {
+    fn error(&self, message: impl Into<String>) -> Error {
+        let offset = match self.toks.get(self.pos).or(self.toks.last()) {
+            Some(&(offset, _)) => offset,
+            None => 0,
+        };
+
+        Error::Ddl {
+            line: self.sql[..offset].matches('\n').count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.toks.get(self.pos).map(|&(_, t)| t)
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.peek() == Some(token);
+        self.pos += found as usize;
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), Error> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{token}`"))),
+        }
+    }
+
+    fn peek_kw(&self, kw: &str) -> bool {
+        self.peek().is_some_and(|t| t.eq_ignore_ascii_case(kw))
+    }
+
+    fn eat_kw(&mut self, kw: &str) -> bool {
+        let found = self.peek_kw(kw);
+        self.pos += found as usize;
+        found
+    }
+
+    /// Consume all of `kws` in sequence, or nothing
+    fn eat_kws(&mut self, kws: &[&str]) -> bool {
+        let found = kws
+            .iter()
+            .enumerate()
+            .all(|(i, kw)| self.toks.get(self.pos + i).is_some_and(|&(_, t)| t.eq_ignore_ascii_case(kw)));
+
+        if found {
+            self.pos += kws.len();
+        }
+
+        found
+    }
+
+    fn expect_kw(&mut self, kw: &str) -> Result<(), Error> {
+        match self.eat_kw(kw) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{}`", kw.to_ascii_uppercase()))),
+        }
+    }
+
+    /// Whether the element starts with a table constraint keyword
+    fn is_constraint(&self) -> bool {
+        ["constraint", "primary", "foreign", "unique", "check", "exclude"].iter().any(|kw| self.peek_kw(kw))
+    }
+
+    /// Parse an identifier, returning whether it was quoted
+    fn ident_quoted(&mut self) -> Result<(String, bool), Error> {
+        match self.peek() {
+            Some(t) if t.starts_with('"') && t.len() >= 2 => {
+                self.next();
+                Ok((t[1..t.len() - 1].replace("\"\"", "\""), true))
+            }
+            Some(t) if is_word_token(t) => {
+                self.next();
+                Ok((t.to_lowercase(), false))
+            }
+            _ => Err(self.error("expected identifier")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        self.ident_quoted().map(|(name, _)| name)
+    }
+
+    /// Parse `[schema.]name`
+    fn qualified(&mut self) -> Result<(Option<String>, String), Error> {
+        let name = self.ident()?;
+
+        match self.eat(".") {
+            true => Ok((Some(name), self.ident()?)),
+            false => Ok((None, name)),
+        }
+    }
+
+    /// Parse a comma-separated list of identifiers
+    fn idents(self) -> Result<Vec<String>, Error> {
+        self.split_commas().iter_mut().map(Cursor::ident).collect()
+    }
+
+    /// Parse a string constant
+    fn string(&mut self) -> Result<String, Error> {
+        let token = self.peek().unwrap_or_default();
+
+        let string = if let Some(inner) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            inner.replace("''", "'")
+        } else if let Some(inner) = token
+            .get(2..token.len().saturating_sub(1))
+            .filter(|_| token.len() >= 3 && token[..2].eq_ignore_ascii_case("e'") && token.ends_with('\''))
+        {
+            unescape(inner)
+        } else if let Some(tag_len) = token.strip_prefix('$').and_then(|t| t.find('$')).map(|i| i + 2) {
+            // $tag$...$tag$
+            if token.len() < tag_len * 2 || !token.ends_with(&token[..tag_len]) {
+                return Err(self.error("unterminated dollar-quoted string"));
+            }
+
+            token[tag_len..token.len() - tag_len].to_owned()
+        } else {
+            return Err(self.error("expected string"));
+        };
+
+        self.next();
+
+        Ok(string)
+    }
+
+    /// Parse a parenthesized group, returning a cursor over its contents
+    fn group(&mut self) -> Result<Cursor<'a, 's>, Error> {
+        self.expect("(")?;
+
+        let start = self.pos;
+        let mut depth = 1;
+
+        while let Some(token) = self.next() {
+            match token {
+                "(" => depth += 1,
+                ")" if depth == 1 => {
+                    return Ok(Cursor {
+                        sql: self.sql,
+                        toks: &self.toks[start..self.pos - 1],
+                        pos: 0,
+                    })
+                }
+                ")" => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Err(self.error("unclosed `(`"))
+    }
+
+    /// Split the remaining tokens on commas outside of parentheses
+    fn split_commas(self) -> Vec<Cursor<'a, 's>> {
+        let toks = &self.toks[self.pos..];
+
+        let mut parts = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+
+        for (i, &(_, token)) in toks.iter().enumerate() {
+            match token {
+                "(" | "[" => depth += 1,
+                ")" | "]" => depth -= 1,
+                "," if depth == 0 => {
+                    parts.push(&toks[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+
+        parts.push(&toks[start..]);
+
+        parts
+            .into_iter()
+            .map(|toks| Cursor {
+                sql: self.sql,
+                toks,
+                pos: 0,
+            })
+            .collect()
+    }
+}
+
+fn is_word_token(token: &str) -> bool {
+    token.bytes().next().is_some_and(|b| b.is_ascii_alphabetic() || b == b'_' || !b.is_ascii())
+}
+
+/// Backslash escapes of `E'...'` strings
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some(c) => out.push(c),
+                None => {}
+            },
+            '\'' => {
+                // doubled quote
+                chars.next();
+                out.push('\'');
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"
+        CREATE SCHEMA thorn_ddl;
+        SET search_path TO thorn_ddl;
+
+        CREATE TYPE mood AS ENUM ('happy', 'it''s complicated');
+        CREATE TYPE floatrange AS RANGE (subtype = double precision);
+        CREATE TYPE address AS (street text, zip character varying(10), moods mood[]);
+        CREATE DOMAIN email AS text CHECK (VALUE LIKE '%@%');
+        CREATE DOMAIN work_email email;
+
+        -- users; of the system
+        CREATE TABLE "Users" (
+            id bigserial PRIMARY KEY,
+            name character varying(40) NOT NULL,
+            mood mood DEFAULT 'happy'::mood,
+            seen timestamp(3) with time zone,
+            tags text[],
+            contact work_email,
+            contacts email[],
+            CHECK (name <> '')
+        );
+
+        CREATE TABLE thorn_ddl.posts (
+            id int8 NOT NULL,
+            author bigint REFERENCES "Users",
+            scores floatrange,
+            location address,
+            CONSTRAINT posts_pkey PRIMARY KEY (id)
+        );
+
+        ALTER TABLE ONLY thorn_ddl.posts ADD COLUMN body text, ADD CONSTRAINT posts_self_fkey FOREIGN KEY (id) REFERENCES posts (id);
+
+        CREATE FUNCTION post_count(author int8, double precision, OUT n int8) LANGUAGE sql AS $$ SELECT 1; $$;
+        CREATE FUNCTION touch() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RETURN NEW; END $$;
+
+        COMMENT ON TABLE "Users" IS 'Registered users';
+        COMMENT ON COLUMN thorn_ddl.posts.body IS E'Post\ncontent';
+        COMMENT ON TYPE mood IS 'How they feel';
+        COMMENT ON TYPE address IS 'Postal address';
+        COMMENT ON FUNCTION post_count(int8, double precision) IS 'Posts by author';
+    "#;
+
+    /// Partitioned and typed tables, as written in migrations
+    const PARTITIONS: &str = r#"
+        CREATE SCHEMA thorn_dump;
+        SET search_path TO thorn_dump;
+
+        CREATE TYPE point3 AS (x float8, y float8, z float8);
+        CREATE TABLE accounts (id int8 PRIMARY KEY, name text NOT NULL);
+        CREATE TABLE events (
+            id int8 NOT NULL,
+            account int8 REFERENCES accounts,
+            at timestamptz NOT NULL,
+            body text,
+            PRIMARY KEY (id, at)
+        ) PARTITION BY RANGE (at);
+        CREATE TABLE events_2024 PARTITION OF events FOR VALUES FROM ('2024-01-01') TO ('2025-01-01');
+        CREATE TABLE events_default PARTITION OF events (body WITH OPTIONS NOT NULL) DEFAULT;
+        CREATE TABLE points OF point3 (x WITH OPTIONS NOT NULL, PRIMARY KEY (x));
+        CREATE TABLE recent AS SELECT * FROM events;
+        ALTER TABLE recent ADD COLUMN seen bool;
+        CREATE FUNCTION f(a int4) RETURNS int4 LANGUAGE sql AS $body$ SELECT $1 $body$;
+        COMMENT ON TABLE events IS 'Account events';
+    "#;
+
+    /// `pg_dump --schema-only` output of [`PARTITIONS`], without ownership statements
+    const PG_DUMP: &str = r#"
+SET statement_timeout = 0;
+SET lock_timeout = 0;
+SET idle_in_transaction_session_timeout = 0;
+SET client_encoding = 'UTF8';
+SET standard_conforming_strings = on;
+SELECT pg_catalog.set_config('search_path', '', false);
+SET check_function_bodies = false;
+SET xmloption = content;
+SET client_min_messages = warning;
+SET row_security = off;
+
+--
+-- Name: thorn_dump; Type: SCHEMA; Schema: -; Owner: -
+--
+
+CREATE SCHEMA thorn_dump;
+
+CREATE TYPE thorn_dump.point3 AS (
+	x double precision,
+	y double precision,
+	z double precision
+);
+
+CREATE FUNCTION thorn_dump.f(a integer) RETURNS integer
+    LANGUAGE sql
+    AS $_$ SELECT $1 $_$;
+
+SET default_tablespace = '';
+
+SET default_table_access_method = heap;
+
+CREATE TABLE thorn_dump.accounts (
+    id bigint NOT NULL,
+    name text NOT NULL
+);
+
+CREATE TABLE thorn_dump.events (
+    id bigint NOT NULL,
+    account bigint,
+    at timestamp with time zone NOT NULL,
+    body text
+)
+PARTITION BY RANGE (at);
+
+COMMENT ON TABLE thorn_dump.events IS 'Account events';
+
+CREATE TABLE thorn_dump.events_2024 (
+    id bigint NOT NULL,
+    account bigint,
+    at timestamp with time zone NOT NULL,
+    body text
+);
+
+CREATE TABLE thorn_dump.events_default (
+    id bigint NOT NULL,
+    account bigint,
+    at timestamp with time zone NOT NULL,
+    body text NOT NULL
+);
+
+CREATE TABLE thorn_dump.points OF thorn_dump.point3 (
+    x NOT NULL
+);
+
+CREATE TABLE thorn_dump.recent AS
+ SELECT events.id,
+    events.account,
+    events.at,
+    events.body
+   FROM thorn_dump.events
+  WITH NO DATA;
+
+ALTER TABLE thorn_dump.recent ADD COLUMN seen boolean;
+
+ALTER TABLE ONLY thorn_dump.events ATTACH PARTITION thorn_dump.events_2024 FOR VALUES FROM ('2024-01-01 00:00:00+00') TO ('2025-01-01 00:00:00+00');
+
+ALTER TABLE ONLY thorn_dump.events ATTACH PARTITION thorn_dump.events_default DEFAULT;
+
+ALTER TABLE ONLY thorn_dump.accounts
+    ADD CONSTRAINT accounts_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY thorn_dump.events
+    ADD CONSTRAINT events_pkey PRIMARY KEY (id, at);
+
+ALTER TABLE ONLY thorn_dump.events_2024
+    ADD CONSTRAINT events_2024_pkey PRIMARY KEY (id, at);
+
+ALTER TABLE ONLY thorn_dump.events_default
+    ADD CONSTRAINT events_default_pkey PRIMARY KEY (id, at);
+
+ALTER TABLE ONLY thorn_dump.points
+    ADD CONSTRAINT points_pkey PRIMARY KEY (x);
+
+ALTER INDEX thorn_dump.events_pkey ATTACH PARTITION thorn_dump.events_2024_pkey;
+
+ALTER INDEX thorn_dump.events_pkey ATTACH PARTITION thorn_dump.events_default_pkey;
+
+ALTER TABLE thorn_dump.events
+    ADD CONSTRAINT events_account_fkey FOREIGN KEY (account) REFERENCES thorn_dump.accounts(id);
+"#;
+
+    #[test]
+    fn test_parse_ddl() {
+        let model = parse_ddl(FIXTURE).unwrap();
+        let schema = model.schema("thorn_ddl").unwrap();
+
+        let users = &schema.tables[0];
+        assert_eq!(users.name, "Users");
+        assert_eq!(users.comment.as_deref(), Some("Registered users"));
+        assert_eq!(users.primary_key, ["id"]);
+
+        let columns: Vec<_> = users.columns.iter().map(|c| (&*c.name, &*c.ty.name, c.nullable)).collect();
+        assert_eq!(
+            columns,
+            [
+                ("id", "int8", false),
+                ("name", "varchar", false),
+                ("mood", "mood", true),
+                ("seen", "timestamptz", true),
+                ("tags", "_text", true),
+                ("contact", "text", true),
+                ("contacts", "_text", true),
+            ]
+        );
+        assert_eq!(users.columns[2].ty, TypeRef::new(0, "thorn_ddl", "mood"));
+        assert_eq!(users.columns[4].ty.oid, Type::TEXT_ARRAY.oid());
+
+        let posts = &schema.tables[1];
+        assert_eq!(posts.columns.last().unwrap().comment.as_deref(), Some("Post\ncontent"));
+        assert_eq!(posts.foreign_keys[0].name, "posts_author_fkey");
+        assert_eq!(posts.foreign_keys[0].foreign_table, "Users");
+        assert_eq!(posts.foreign_keys[0].foreign_columns, ["id"]);
+        assert_eq!(posts.foreign_keys[1].name, "posts_self_fkey");
+
+        assert_eq!(schema.enums[0].variants, ["happy", "it's complicated"]);
+        assert_eq!(schema.ranges[0].multirange_name, "floatmultirange");
+        assert_eq!(schema.ranges[0].subtype.name, "float8");
+
+        let address = &schema.composites[0];
+        assert_eq!(address.comment.as_deref(), Some("Postal address"));
+        let fields: Vec<_> = address.fields.iter().map(|f| (&*f.name, &*f.ty.name)).collect();
+        assert_eq!(fields, [("street", "text"), ("zip", "varchar"), ("moods", "_mood")]);
+        assert_eq!(posts.columns[3].ty, TypeRef::new(0, "thorn_ddl", "address"));
+
+        assert_eq!(schema.functions.len(), 1);
+        let args: Vec<_> = schema.functions[0].args.iter().map(|a| (&*a.name, &*a.ty.name)).collect();
+        assert_eq!(args, [("author", "int8"), ("__arg1", "float8")]);
+        assert_eq!(schema.functions[0].comment.as_deref(), Some("Posts by author"));
+
+        let err = parse_ddl("CREATE TABLE t (id int4);\nALTER TABLE missing ADD COLUMN x int4;").unwrap_err();
+        assert_eq!(err.to_string(), "DDL error at line 2: unknown table \"public.missing\"");
+
+        for sql in ["COMMENT ON TABLE t IS $$", "COMMENT ON TABLE t IS $tag$oops$$;"] {
+            let err = parse_ddl(&format!("CREATE TABLE t (id int4);\n{sql}")).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "DDL error at line 2: unterminated dollar-quoted string"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_partitions() {
+        let model = parse_ddl(PARTITIONS).unwrap();
+        let schema = model.schema("thorn_dump").unwrap();
+
+        let tables: Vec<_> = schema.tables.iter().map(|t| &*t.name).collect();
+        assert_eq!(
+            tables,
+            ["accounts", "events", "events_2024", "events_default", "points"]
+        );
+
+        let events = &schema.tables[1];
+
+        for partition in &schema.tables[2..4] {
+            assert_eq!(partition.columns.len(), events.columns.len());
+            assert_eq!(partition.primary_key, ["id", "at"]);
+            assert_eq!(partition.foreign_keys, events.foreign_keys);
+            assert_eq!(partition.comment, None);
+        }
+
+        assert!(schema.tables[2].columns[3].nullable);
+        assert!(!schema.tables[3].columns[3].nullable);
+
+        let points = &schema.tables[4];
+        let columns: Vec<_> = points.columns.iter().map(|c| (&*c.name, &*c.ty.name, c.nullable)).collect();
+        assert_eq!(
+            columns,
+            [("x", "float8", false), ("y", "float8", true), ("z", "float8", true)]
+        );
+        assert_eq!(points.primary_key, ["x"]);
+
+        assert_eq!(parse_ddl(PG_DUMP).unwrap(), model);
+    }
+
+    /// Set `THORN_TEST_DB` to a connection string to run
+    #[tokio::test]
+    #[ignore]
+    async fn test_ddl_matches_introspection() {
+        let db = std::env::var("THORN_TEST_DB").expect("THORN_TEST_DB is not set");
+        let (client, conn) = pgt::connect(&db, pgt::NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        for (schema, fixture) in [
+            ("thorn_ddl", FIXTURE),
+            ("thorn_dump", PARTITIONS),
+            ("thorn_dump", PG_DUMP),
+        ] {
+            assert_matches_introspection(&client, schema, fixture).await;
+        }
+    }
+
+    async fn assert_matches_introspection(client: &pgt::Client, schema: &str, fixture: &str) {
+        client.batch_execute(&format!("DROP SCHEMA IF EXISTS {schema} CASCADE")).await.unwrap();
+        client.batch_execute(fixture).await.unwrap();
+
+        let res = super::super::introspect(client, &[schema.to_owned()]).await;
+
+        client.batch_execute(&format!("DROP SCHEMA {schema} CASCADE")).await.unwrap();
+
+        let mut introspected = res.unwrap();
+
+        // user-defined types are not known by oid offline
+        let user_oid = |oid: &mut u32| {
+            if *oid >= 16384 {
+                *oid = 0;
+            }
+        };
+
+        for schema in &mut introspected.schemas {
+            for enum_ in &mut schema.enums {
+                user_oid(&mut enum_.oid);
+                user_oid(&mut enum_.array_oid);
+            }
+
+            for range in &mut schema.ranges {
+                user_oid(&mut range.oid);
+                user_oid(&mut range.array_oid);
+                user_oid(&mut range.multirange_oid);
+                user_oid(&mut range.multirange_array_oid);
+                user_oid(&mut range.subtype.oid);
+            }
+
+            for composite in &mut schema.composites {
+                user_oid(&mut composite.oid);
+                user_oid(&mut composite.array_oid);
+                composite.fields.iter_mut().for_each(|f| user_oid(&mut f.ty.oid));
+            }
+
+            for table in &mut schema.tables {
+                table.columns.iter_mut().for_each(|c| user_oid(&mut c.ty.oid));
+            }
+
+            for func in &mut schema.functions {
+                func.args.iter_mut().for_each(|a| user_oid(&mut a.ty.oid));
+            }
+        }
+
+        let parsed = parse_ddl(fixture).unwrap();
+
+        // views and tables created with `AS SELECT` are not parsed
+        for schema in &mut introspected.schemas {
+            schema.tables.retain(|t| t.name != "recent");
+        }
+
+        assert_eq!(parsed, introspected);
+    }
+}
@@ -0,0 +1,13 @@
+use super::{Emitter, Error, SchemaModel};
+
+/// Emits the [`SchemaModel`] itself as pretty-printed JSON, for use by other tooling
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonEmitter;
+
+impl Emitter for JsonEmitter {
+    fn emit(&self, model: &SchemaModel) -> Result<String, Error> {
+        let mut out = serde_json::to_string_pretty(model)?;
+        out.push('\n');
+        Ok(out)
+    }
+}
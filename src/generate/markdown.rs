@@ -0,0 +1,168 @@
+use std::fmt::Write;
+
+use super::{Emitter, Error, SchemaModel, TypeRef};
+
+/// Emits Markdown documentation, with a section per schema
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownEmitter;
+
+impl Emitter for MarkdownEmitter {
+    fn emit(&self, model: &SchemaModel) -> Result<String, Error> {
+        let mut out = String::new();
+
+        for schema in &model.schemas {
+            writeln!(out, "# Schema `{}`\n", schema.name)?;
+
+            let type_name = |ty: &TypeRef| display_type(&schema.name, ty);
+
+            if !schema.tables.is_empty() {
+                out.push_str("## Tables\n\n");
+            }
+
+            for table in &schema.tables {
+                writeln!(out, "### `{}`\n", table.name)?;
+
+                if let Some(ref comment) = table.comment {
+                    writeln!(out, "{comment}\n")?;
+                }
+
+                out.push_str("| Column | Type | Nullable | Description |\n");
+                out.push_str("|--------|------|----------|-------------|\n");
+
+                for col in &table.columns {
+                    let key = match table.primary_key.contains(&col.name) {
+                        true => " (PK)",
+                        false => "",
+                    };
+
+                    writeln!(
+                        out,
+                        "| `{}`{key} | `{}` | {} | {} |",
+                        col.name,
+                        type_name(&col.ty),
+                        if col.nullable { "yes" } else { "no" },
+                        col.comment.as_deref().map(table_cell).unwrap_or_default(),
+                    )?;
+                }
+
+                out.push('\n');
+
+                for fk in &table.foreign_keys {
+                    writeln!(
+                        out,
+                        "- Foreign key `{}`: ({}) references `{}.{}` ({})",
+                        fk.name,
+                        code_list(&fk.columns),
+                        fk.foreign_schema,
+                        fk.foreign_table,
+                        code_list(&fk.foreign_columns),
+                    )?;
+                }
+
+                if !table.foreign_keys.is_empty() {
+                    out.push('\n');
+                }
+            }
+
+            if !schema.enums.is_empty() {
+                out.push_str("## Enums\n\n");
+            }
+
+            for enum_ in &schema.enums {
+                writeln!(out, "### `{}`\n", enum_.name)?;
+
+                if let Some(ref comment) = enum_.comment {
+                    writeln!(out, "{comment}\n")?;
+                }
+
+                for variant in &enum_.variants {
+                    writeln!(out, "- `{variant}`")?;
+                }
+
+                out.push('\n');
+            }
+
+            if !schema.ranges.is_empty() {
+                out.push_str("## Ranges\n\n");
+            }
+
+            for range in &schema.ranges {
+                write!(
+                    out,
+                    "- `{}` of `{}`, with multirange `{}`",
+                    range.name,
+                    type_name(&range.subtype),
+                    range.multirange_name
+                )?;
+
+                match range.comment {
+                    Some(ref comment) => writeln!(out, ": {}", table_cell(comment))?,
+                    None => out.push('\n'),
+                }
+            }
+
+            if !schema.ranges.is_empty() {
+                out.push('\n');
+            }
+
+            if !schema.composites.is_empty() {
+                out.push_str("## Composite types\n\n");
+            }
+
+            for composite in &schema.composites {
+                writeln!(out, "### `{}`\n", composite.name)?;
+
+                if let Some(ref comment) = composite.comment {
+                    writeln!(out, "{comment}\n")?;
+                }
+
+                out.push_str("| Field | Type |\n");
+                out.push_str("|-------|------|\n");
+
+                for field in &composite.fields {
+                    writeln!(out, "| `{}` | `{}` |", field.name, type_name(&field.ty))?;
+                }
+
+                out.push('\n');
+            }
+
+            if !schema.functions.is_empty() {
+                out.push_str("## Functions\n\n");
+            }
+
+            for func in &schema.functions {
+                let args = func.args.iter().map(|arg| format!("{} {}", arg.name, type_name(&arg.ty)));
+
+                writeln!(out, "### `{}({})`\n", func.name, args.collect::<Vec<_>>().join(", "))?;
+
+                if let Some(ref comment) = func.comment {
+                    writeln!(out, "{comment}\n")?;
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// SQL-style type name, such as `int4[]`, qualified when outside of `pg_catalog` and `schema`
+fn display_type(schema: &str, ty: &TypeRef) -> String {
+    let (name, array) = match ty.name.strip_prefix('_') {
+        Some(elem) => (elem, "[]"),
+        None => (&*ty.name, ""),
+    };
+
+    match &*ty.schema {
+        "" | "pg_catalog" => format!("{name}{array}"),
+        s if s == schema => format!("{name}{array}"),
+        s => format!("{s}.{name}{array}"),
+    }
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn code_list(names: &[String]) -> String {
+    names.iter().map(|n| format!("`{n}`")).collect::<Vec<_>>().join(", ")
+}
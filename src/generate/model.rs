@@ -0,0 +1,165 @@
+use pg::Oid;
+use serde::{Deserialize, Serialize};
+
+/// Database schemas as seen by the generator, independent of the output format.
+///
+/// Produced by [`introspect`](super::introspect) and consumed by an [`Emitter`](super::Emitter).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaModel {
+    /// Schemas sorted by name
+    pub schemas: Vec<Schema>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub name: String,
+    pub tables: Vec<Table>,
+    pub enums: Vec<Enum>,
+    pub ranges: Vec<RangeType>,
+    pub composites: Vec<Composite>,
+    pub functions: Vec<Function>,
+}
+
+/// Reference to a builtin or user-defined type
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TypeRef {
+    /// Type oid, or `0` if unknown
+    pub oid: Oid,
+    pub schema: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    pub name: String,
+    pub comment: Option<String>,
+    /// Columns in ordinal position
+    pub columns: Vec<Column>,
+    /// Names of the primary key columns, empty if there is no primary key
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub ty: TypeRef,
+    pub nullable: bool,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub foreign_schema: String,
+    pub foreign_table: String,
+    pub foreign_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Enum {
+    /// Type oid, or `0` if unknown
+    pub oid: Oid,
+    /// Oid of the array type, or `0` if unknown
+    pub array_oid: Oid,
+    pub name: String,
+    pub comment: Option<String>,
+    /// Variant labels in sort order
+    pub variants: Vec<String>,
+}
+
+/// User-defined range type and its multirange
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RangeType {
+    /// Type oid, or `0` if unknown
+    pub oid: Oid,
+    /// Oid of the array type, or `0` if unknown
+    pub array_oid: Oid,
+    pub name: String,
+    pub comment: Option<String>,
+    pub subtype: TypeRef,
+    pub multirange_oid: Oid,
+    pub multirange_array_oid: Oid,
+    pub multirange_name: String,
+}
+
+/// User-defined composite type, created with `CREATE TYPE ... AS (...)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Composite {
+    /// Type oid, or `0` if unknown
+    pub oid: Oid,
+    /// Oid of the array type, or `0` if unknown
+    pub array_oid: Oid,
+    pub name: String,
+    pub comment: Option<String>,
+    /// Fields in attribute order
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeRef,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    pub comment: Option<String>,
+    pub args: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Argument {
+    /// Argument name, filled in as `__argN` for unnamed arguments
+    pub name: String,
+    pub ty: TypeRef,
+}
+
+impl SchemaModel {
+    /// Find the schema named `name`
+    pub fn schema(&self, name: &str) -> Option<&Schema> {
+        self.schemas.iter().find(|s| s.name == name)
+    }
+
+    /// Find the schema named `name`, adding an empty one if missing
+    pub fn schema_mut(&mut self, name: &str) -> &mut Schema {
+        let idx = match self.schemas.iter().position(|s| s.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.schemas.push(Schema {
+                    name: name.to_owned(),
+                    ..Schema::default()
+                });
+
+                self.schemas.len() - 1
+            }
+        };
+
+        &mut self.schemas[idx]
+    }
+
+    /// Sort schemas and their items by name
+    pub fn sort(&mut self) {
+        self.schemas.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for schema in &mut self.schemas {
+            schema.tables.sort_by(|a, b| a.name.cmp(&b.name));
+            schema.enums.sort_by(|a, b| a.name.cmp(&b.name));
+            schema.ranges.sort_by(|a, b| a.name.cmp(&b.name));
+            schema.composites.sort_by(|a, b| a.name.cmp(&b.name));
+            schema.functions.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+}
+
+impl TypeRef {
+    pub fn new(oid: Oid, schema: impl Into<String>, name: impl Into<String>) -> Self {
+        TypeRef {
+            oid,
+            schema: schema.into(),
+            name: name.into(),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use heck::*;
+use pg::{Oid, Type};
+
+use super::{Config, Emitter, Error, Schema, SchemaModel, TypeRef};
+
+const COMMENT_WIDTH: usize = 70;
+
+/// Emits `thorn::tables!`, `thorn::enums!` and `thorn::functions!` definitions, along with `Type` statics
+/// for enums, ranges and composite types and arrays of them.
+///
+/// With a single schema the definitions are written at the top level, otherwise each schema is
+/// written to its own `pub mod <schema>`, and user-defined types of other schemas are referred
+/// to as `super::<schema>::TYPE`.
+///
+/// Types that are neither builtin, generated nor mapped in the [`Config`] are an error.
+#[derive(Debug, Clone, Default)]
+pub struct RustEmitter {
+    pub config: Config,
+}
+
+impl RustEmitter {
+    pub fn new(config: Config) -> Self {
+        RustEmitter { config }
+    }
+}
+
+impl Emitter for RustEmitter {
+    fn emit(&self, model: &SchemaModel) -> Result<String, Error> {
+        let mut custom_types = HashMap::new();
+
+        for schema in &model.schemas {
+            let names = schema.enums.iter().map(|e| &e.name).chain(schema.composites.iter().map(|c| &c.name));
+            let names = names.chain(schema.ranges.iter().flat_map(|r| [&r.name, &r.multirange_name]));
+
+            // along with their arrays, named `_<type>`
+            for name in names {
+                let ty = name.to_shouty_snake_case();
+
+                custom_types.insert((&*schema.name, Cow::Owned(format!("_{name}"))), format!("{ty}_ARRAY"));
+                custom_types.insert((&*schema.name, Cow::Borrowed(&**name)), ty);
+            }
+        }
+
+        let emitter = Rust {
+            config: &self.config,
+            custom_types,
+        };
+
+        let mut out = String::from("#![rustfmt::skip]\n");
+
+        if let [ref schema] = model.schemas[..] {
+            emitter.write_schema(&mut out, schema)?;
+
+            return Ok(out);
+        }
+
+        for schema in &model.schemas {
+            let mut body = String::new();
+
+            emitter.write_schema(&mut body, schema)?;
+
+            writeln!(out, "\npub mod {} {{", schema.name.to_snake_case())?;
+
+            for line in body.lines() {
+                match line.is_empty() {
+                    true => out.push('\n'),
+                    false => writeln!(out, "    {line}")?,
+                }
+            }
+
+            out.push_str("}\n");
+        }
+
+        Ok(out)
+    }
+}
+
+struct Rust<'a> {
+    config: &'a Config,
+
+    /// The static name of user-defined types, by schema and type name
+    custom_types: HashMap<(&'a str, Cow<'a, str>), String>,
+}
+
+impl Rust<'_> {
+    /// Expression for the `Type` of `ty`, as seen from the module of `schema`
+    fn type_path(&self, schema: &str, ty: &TypeRef, context: impl FnOnce() -> String) -> Result<String, Error> {
+        if let Some(expr) = self.config.type_override(ty) {
+            return Ok(expr.to_owned());
+        }
+
+        if let Some(ty) = Type::from_oid(ty.oid) {
+            return Ok(PType(ty).to_string());
+        }
+
+        match self.custom_types.get(&(&*ty.schema, Cow::Borrowed(&*ty.name))) {
+            Some(name) if ty.schema == schema => Ok(format!("{name}.clone()")),
+            Some(name) => Ok(format!("super::{}::{name}.clone()", ty.schema.to_snake_case())),
+            None => Err(Error::UnknownType {
+                ty: match ty.schema.is_empty() {
+                    true => ty.oid.to_string(),
+                    false => format!("{}.{}", ty.schema, ty.name),
+                },
+                context: context(),
+            }),
+        }
+    }
+
+    fn write_schema(&self, out: &mut String, schema: &Schema) -> Result<(), Error> {
+        let schema_name = schema.name.to_upper_camel_case();
+
+        let mut out_funcs = String::new();
+        let mut out_enums = String::new();
+        let mut out_tables = String::new();
+
+        let mut uses_nullable = false;
+        let mut uses_type = false;
+
+        // Funcs
+        {
+            let out = &mut out_funcs;
+
+            out.push_str("thorn::functions! {\n");
+
+            for func in &schema.functions {
+                if let Some(ref comment) = func.comment {
+                    for line in textwrap::wrap(comment, COMMENT_WIDTH) {
+                        writeln!(out, "    /// {line}")?;
+                    }
+                }
+
+                write!(out, "    pub extern \"pg\" fn {}(", func.name)?;
+
+                for (idx, arg) in func.args.iter().enumerate() {
+                    let ty = self.type_path(&schema.name, &arg.ty, || {
+                        format!(
+                            "argument \"{}\" of function \"{}.{}\"",
+                            arg.name, schema.name, func.name
+                        )
+                    })?;
+
+                    uses_type = true;
+
+                    write!(out, "{}: {}", arg.name, ty)?;
+
+                    if (idx + 1) < func.args.len() {
+                        out.push_str(", ");
+                    }
+                }
+
+                writeln!(out, ") in {schema_name};")?;
+            }
+
+            out.push_str("}\n\n");
+        }
+
+        // Enums
+        {
+            let out = &mut out_enums;
+
+            let mut lazy_statics = String::new();
+
+            out.push_str("thorn::enums! {\n");
+
+            for enum_ in &schema.enums {
+                if let Some(ref comment) = enum_.comment {
+                    for line in textwrap::wrap(comment, COMMENT_WIDTH) {
+                        writeln!(out, "    /// {line}")?;
+                    }
+                }
+
+                let enum_name = enum_.name.to_upper_camel_case();
+
+                uses_type = true;
+
+                writeln!(
+                    lazy_statics,
+                    "/// See [{enum_name}] for full documentation\npub static {}: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| <{enum_name} as thorn::EnumType>::ty({}));\n",
+                    enum_.name.to_shouty_snake_case(),
+                    enum_.oid
+                )?;
+
+                write_array_static(&mut lazy_statics, &enum_.name, enum_.array_oid, &schema.name)?;
+
+                // This isn't strictly necessary, but it's kind of a pointless rule for SQL enums
+                out.push_str("    #[allow(clippy::enum_variant_names)]\n");
+
+                writeln!(out, "    pub enum {enum_name} in {schema_name} {{")?;
+
+                for variant in &enum_.variants {
+                    let variant_name = variant.to_upper_camel_case();
+
+                    writeln!(out, "        {variant_name},")?;
+                }
+
+                out.push_str("    }\n");
+            }
+
+            out.push_str("}\n\n");
+            out.push_str(&lazy_statics);
+        }
+
+        // Ranges
+        {
+            let out = &mut out_enums;
+
+            for range in &schema.ranges {
+                let subtype = self.type_path(&schema.name, &range.subtype, || {
+                    format!("range type \"{}.{}\"", schema.name, range.name)
+                })?;
+
+                uses_type = true;
+
+                let name = range.name.to_shouty_snake_case();
+
+                writeln!(
+                    out,
+                    "/// Range type `{}` of `{subtype}`\npub static {name}: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| thorn::range::range_type({:?}, {}, {subtype}, {:?}));\n",
+                    range.name, range.name, range.oid, schema.name,
+                )?;
+
+                writeln!(
+                    out,
+                    "/// Multirange type of [`{name}`]\npub static {}: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| thorn::range::multirange_type({:?}, {}, {subtype}, {:?}));\n",
+                    range.multirange_name.to_shouty_snake_case(), range.multirange_name, range.multirange_oid, schema.name,
+                )?;
+
+                write_array_static(out, &range.name, range.array_oid, &schema.name)?;
+                write_array_static(out, &range.multirange_name, range.multirange_array_oid, &schema.name)?;
+            }
+        }
+
+        // Composites
+        {
+            let out = &mut out_enums;
+
+            for composite in &schema.composites {
+                let mut fields = Vec::new();
+
+                for field in &composite.fields {
+                    let ty = self.type_path(&schema.name, &field.ty, || {
+                        format!(
+                            "field \"{}\" of composite type \"{}.{}\"",
+                            field.name, schema.name, composite.name
+                        )
+                    })?;
+
+                    fields.push(format!("thorn::pg::Field::new({:?}.to_owned(), {ty})", field.name));
+                }
+
+                uses_type = true;
+
+                match composite.comment {
+                    Some(ref comment) => {
+                        for line in textwrap::wrap(comment, COMMENT_WIDTH) {
+                            writeln!(out, "/// {line}")?;
+                        }
+                    }
+                    None => writeln!(out, "/// Composite type `{}`", composite.name)?,
+                }
+
+                writeln!(
+                    out,
+                    "pub static {}: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| Type::new({:?}.to_owned(), {}, thorn::pg::Kind::Composite(vec![{}]), {:?}.to_owned()));\n",
+                    composite.name.to_shouty_snake_case(), composite.name, composite.oid, fields.join(", "), schema.name,
+                )?;
+
+                write_array_static(out, &composite.name, composite.array_oid, &schema.name)?;
+            }
+        }
+
+        // Tables
+        {
+            let out = &mut out_tables;
+
+            out.push_str("thorn::tables! {\n");
+
+            for table in &schema.tables {
+                if let Some(ref comment) = table.comment {
+                    for line in textwrap::wrap(comment, COMMENT_WIDTH) {
+                        writeln!(out, "    /// {line}")?;
+                    }
+                }
+
+                let table_name = table.name.to_upper_camel_case();
+
+                writeln!(out, "    pub struct {table_name} in {schema_name} {{")?;
+
+                for col in &table.columns {
+                    let ty = self.type_path(&schema.name, &col.ty, || {
+                        format!("column \"{}.{}.{}\"", schema.name, table.name, col.name)
+                    })?;
+
+                    uses_type = true;
+
+                    let column_name = col.name.to_upper_camel_case();
+
+                    if let Some(ref comment) = col.comment {
+                        for line in textwrap::wrap(comment, 66) {
+                            writeln!(out, "        /// {line}")?;
+                        }
+                    }
+
+                    if col.nullable {
+                        uses_nullable = true;
+
+                        writeln!(out, "        {column_name}: Nullable({}),", ty)?;
+                    } else {
+                        writeln!(out, "        {column_name}: {},", ty)?;
+                    }
+                }
+
+                out.push_str("    }\n\n");
+            }
+
+            out.push_str("}\n");
+        }
+
+        if uses_nullable {
+            out.push_str("use thorn::table::Nullable;\n\n");
+        }
+
+        if uses_type {
+            out.push_str("use thorn::pg::Type;\n\n");
+        }
+
+        *out += &out_funcs;
+        *out += &out_enums;
+        *out += &out_tables;
+
+        Ok(())
+    }
+}
+
+/// Write the static of the array type of the user-defined type `elem`, named `<ELEM>_ARRAY`
+fn write_array_static(out: &mut String, elem: &str, oid: Oid, schema: &str) -> fmt::Result {
+    let elem_static = elem.to_shouty_snake_case();
+
+    writeln!(
+        out,
+        "/// Array of [`{elem_static}`]\npub static {elem_static}_ARRAY: std::sync::LazyLock<Type> = std::sync::LazyLock::new(|| Type::new({:?}.to_owned(), {oid}, thorn::pg::Kind::Array({elem_static}.clone()), {schema:?}.to_owned()));\n",
+        format!("_{elem}"),
+    )
+}
+
+struct PType(pub Type);
+
+impl fmt::Display for PType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Type::{}", format!("{:?}", self.0).to_shouty_snake_case())
+    }
+}
@@ -0,0 +1,110 @@
+//! Minimal SQL tokenizer for post-processing rendered queries.
+//!
+//! Only splits the text into whole tokens: string literals, quoted identifiers, dollar-quoted
+//! strings and comments are single tokens, so their contents are never mistaken for SQL.
+
+/// Iterator over `(offset, token)` pairs of an SQL string, skipping whitespace
+pub(crate) struct Tokens<'a> {
+    sql: &'a str,
+    pos: usize,
+}
+
+pub(crate) fn tokens(sql: &str) -> Tokens<'_> {
+    Tokens { sql, pos: 0 }
+}
+
+fn is_word(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii()
+}
+
+fn is_operator(b: u8) -> bool {
+    matches!(
+        b,
+        b'+' | b'-'
+            | b'*'
+            | b'/'
+            | b'<'
+            | b'>'
+            | b'='
+            | b'~'
+            | b'!'
+            | b'@'
+            | b'#'
+            | b'%'
+            | b'^'
+            | b'&'
+            | b'|'
+            | b'`'
+            | b'?'
+    )
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sql = self.sql;
+        let bytes = sql.as_bytes();
+
+        while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        let b = *bytes.get(start)?;
+
+        // length of the token starting at `start`, up to `end` exclusive
+        let find = |pat: &str, from: usize| sql[from..].find(pat).map(|i| from + i);
+
+        let end = match b {
+            // E'' strings allow backslash escapes
+            b'E' | b'e' if bytes.get(start + 1) == Some(&b'\'') => quoted(bytes, start + 1, b'\'', true),
+            b'\'' => quoted(bytes, start, b'\'', false),
+            b'"' => quoted(bytes, start, b'"', false),
+            b'-' if bytes.get(start + 1) == Some(&b'-') => find("\n", start).unwrap_or(bytes.len()),
+            b'/' if bytes.get(start + 1) == Some(&b'*') => find("*/", start + 2).map_or(bytes.len(), |i| i + 2),
+            b'$' => {
+                let rest = &bytes[start + 1..];
+                let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                let tag = rest.iter().take_while(|&&b| is_word(b)).count();
+
+                if digits > 0 {
+                    start + 1 + digits
+                } else if rest.get(tag) == Some(&b'$') {
+                    // dollar-quoted string, $$...$$ or $tag$...$tag$
+                    let tag = &sql[start..start + tag + 2];
+                    find(tag, start + tag.len()).map_or(bytes.len(), |i| i + tag.len())
+                } else {
+                    start + 1
+                }
+            }
+            b':' if bytes.get(start + 1) == Some(&b':') => start + 2,
+            b'0'..=b'9' => start + bytes[start..].iter().take_while(|&&b| b.is_ascii_digit() || b == b'.').count(),
+            _ if is_word(b) => start + bytes[start..].iter().take_while(|&&b| is_word(b) || b == b'$').count(),
+            _ if is_operator(b) => start + bytes[start..].iter().take_while(|&&b| is_operator(b)).count(),
+            _ => start + sql[start..].chars().next().map_or(1, char::len_utf8),
+        };
+
+        self.pos = end;
+
+        Some((start, &sql[start..end]))
+    }
+}
+
+/// Returns the end of a quoted token, where doubled quotes are escapes
+fn quoted(bytes: &[u8], open: usize, quote: u8, backslash: bool) -> usize {
+    let mut i = open + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if backslash => i += 1,
+            b if b == quote && bytes.get(i + 1) == Some(&quote) => i += 1,
+            b if b == quote => return i + 1,
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
@@ -8,11 +8,23 @@ pub extern crate tokio_postgres as pgt;
 #[doc(hidden)]
 pub extern crate paste;
 
+#[cfg(feature = "registry")]
+#[doc(hidden)]
+pub extern crate linkme;
+
 #[macro_use]
 pub mod macros;
 
+pub mod builder;
+pub mod debug_sql;
+pub mod escape;
+mod lexer;
 pub mod literal;
 pub mod name;
+pub mod pretty;
+pub mod range;
+pub mod source_map;
+mod spacing;
 pub mod ty;
 
 #[cfg(feature = "extensions")]
@@ -21,6 +33,9 @@ pub mod extensions;
 #[cfg(feature = "generate")]
 pub mod generate;
 
+#[macro_use]
+pub mod registry;
+
 #[macro_use]
 pub mod table;
 
@@ -6,24 +6,71 @@ use std::{
     fmt::{self, Write},
 };
 
-mod private {
-    pub trait Sealed {}
+use crate::{enums::EnumType, escape};
+
+pub use thorn_macros::Literal;
+
+/// Values that can be written directly into SQL, such as `{value}` in [`sql!`](crate::sql).
+///
+/// Writing a literal fails if the value cannot be represented, such as a `u64` larger than `i64::MAX`
+/// or a string containing NUL characters.
+///
+/// Newtypes and unit-only enums declared with [`enums!`](crate::enums) can implement this trait
+/// with `#[derive(Literal)]`.
+pub trait Literal: Sized {
+    /// Write the literal, where `depth` is the nesting level within array and tuple literals.
+    ///
+    /// At depth `0` the value must be a complete SQL expression, such as `'text'`, but nested values are
+    /// written as elements of the array or composite text, such as `"text"`, without quotes or casts.
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result;
 
-    macro_rules! impl_sealed {
-        ($($ty:ty),*) => {$(impl Sealed for $ty {})*}
+    /// The dimensions of an array literal, empty for scalars or `None` if the array is not rectangular
+    #[doc(hidden)]
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        Some(Vec::new())
     }
 
-    impl_sealed!((), bool, i8, i16, i32, i64, f32, f64, &str, String);
+    /// The dimensions of a slice of this type, see [`Literal::array_dims`]
+    #[doc(hidden)]
+    fn slice_dims(slice: &[Self]) -> Option<Vec<usize>> {
+        let mut elements = slice.iter().map(Literal::array_dims);
 
-    impl<T: Sealed, const N: usize> Sealed for [T; N] {}
-    impl<T: Sealed> Sealed for &[T] {}
-    impl<T: Sealed> Sealed for Vec<T> {}
-    impl<T: Sealed> Sealed for &T {}
-}
+        let mut dims = match elements.next() {
+            Some(first) => first?,
+            None => Vec::new(),
+        };
 
-pub trait Literal: Sized + private::Sealed {
+        if elements.any(|other| other.as_ref() != Some(&dims)) {
+            return None;
+        }
+
+        dims.insert(0, slice.len());
+        Some(dims)
+    }
+
+    /// Write a slice of this type, as an array by default
     #[doc(hidden)]
-    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result;
+    fn write_slice(slice: &[Self], w: &mut dyn Write, depth: usize) -> fmt::Result {
+        // multidimensional arrays must have sub-arrays with matching dimensions
+        if Self::slice_dims(slice).is_none() {
+            return Err(fmt::Error);
+        }
+
+        write_nested(w, depth, |w| {
+            w.write_str("{")?;
+
+            for (i, lit) in slice.iter().enumerate() {
+                if i > 0 {
+                    w.write_str(", ")?;
+                }
+
+                lit.write_literal(w, depth + 1)?
+            }
+
+            w.write_str("}")
+        })
+    }
 }
 
 impl<T: Literal> Literal for &T {
@@ -31,6 +78,42 @@ impl<T: Literal> Literal for &T {
     fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
         (**self).write_literal(w, depth)
     }
+
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        (**self).array_dims()
+    }
+}
+
+impl<T: Literal> Literal for Box<T> {
+    #[inline]
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        (**self).write_literal(w, depth)
+    }
+
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        (**self).array_dims()
+    }
+}
+
+/// `None` is written as `NULL`, including as an array element
+impl<T: Literal> Literal for Option<T> {
+    #[inline]
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        match self {
+            Some(value) => value.write_literal(w, depth),
+            None => w.write_str("NULL"),
+        }
+    }
+
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        match self {
+            Some(value) => value.array_dims(),
+            None => Some(Vec::new()),
+        }
+    }
 }
 
 impl Literal for () {
@@ -56,24 +139,82 @@ macro_rules! impl_num_lits {
         }
     )*};
 
-    (@FLOAT $($ty:ty),*) => {$(
+    // PostgreSQL has no unsigned types, so unsigned integers must fit in `bigint`, the largest integer type.
+    // Smaller values are written as-is and take the type of their magnitude, so `u32::MAX` becomes `bigint`.
+    (@UINT $($ty:ty),*) => {$(
         impl Literal for $ty {
             fn write_literal(&self, w: &mut dyn Write, _depth: usize) -> fmt::Result {
-                write!(w, "{}", self)
+                if i64::try_from(*self).is_err() {
+                    return Err(fmt::Error);
+                }
+
+                w.write_str(itoa::Buffer::new().format(*self))
             }
         }
     )*};
+
+    (@FLOAT $($ty:ty: $cast:literal),*) => {$(
+        impl Literal for $ty {
+            fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+                let special = match *self {
+                    x if x.is_nan() => "NaN",
+                    <$ty>::INFINITY => "Infinity",
+                    <$ty>::NEG_INFINITY => "-Infinity",
+                    _ => return write!(w, "{}", self),
+                };
+
+                // array elements are parsed with the type of the array
+                match depth {
+                    0 => write!(w, "'{special}'::{}", $cast),
+                    _ => w.write_str(special),
+                }
+            }
+        }
+    )*};
+}
+
+impl_num_lits!(@INT i8, i16, i32, i64, i128);
+impl_num_lits!(@UINT u16, u32, u64);
+impl_num_lits!(@FLOAT f32: "float4", f64: "float8");
+
+/// Slices of bytes are written as `bytea` literals in hex format
+impl Literal for u8 {
+    fn write_literal(&self, w: &mut dyn Write, _depth: usize) -> fmt::Result {
+        w.write_str(itoa::Buffer::new().format(*self))
+    }
+
+    #[inline]
+    fn slice_dims(_slice: &[u8]) -> Option<Vec<usize>> {
+        Some(Vec::new())
+    }
+
+    fn write_slice(slice: &[u8], w: &mut dyn Write, depth: usize) -> fmt::Result {
+        if depth == 0 {
+            return escape::write_bytea(slice, w);
+        }
+
+        let mut buf = String::from("\\x");
+
+        for byte in slice {
+            write!(buf, "{byte:02x}")?;
+        }
+
+        escape::write_array_element(&buf, w)
+    }
 }
 
-impl_num_lits!(@INT i8, i16, i32, i64);
-impl_num_lits!(@FLOAT f32, f64);
+impl Literal for char {
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        (&*self.encode_utf8(&mut [0; 4])).write_literal(w, depth)
+    }
+}
 
 impl Literal for &str {
     fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
         if depth == 0 {
-            write_escaped_string_quoted(self, w)
+            escape::write_string(self, w)
         } else {
-            write_escaped_string_nested(self, w)
+            escape::write_array_element(self, w)
         }
     }
 }
@@ -84,28 +225,22 @@ impl Literal for String {
     }
 }
 
-impl<T: Literal> Literal for &[T] {
-    fn write_literal(&self, mut w: &mut dyn Write, depth: usize) -> fmt::Result {
-        if depth == 0 {
-            w.write_str("'")?;
-        }
-
-        w.write_str("{")?;
-
-        for (i, lit) in self.iter().enumerate() {
-            if i > 0 {
-                w.write_str(", ")?;
-            }
-
-            lit.write_literal(&mut w, depth + 1)?
-        }
+impl Literal for Cow<'_, str> {
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        (&**self).write_literal(w, depth)
+    }
+}
 
-        w.write_str("}")?;
-        if depth == 0 {
-            w.write_str("'")?;
-        }
+/// Slices are written as arrays, and nested slices as multidimensional arrays, which must be rectangular
+impl<T: Literal> Literal for &[T] {
+    #[inline]
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        T::write_slice(self, w, depth)
+    }
 
-        Ok(())
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        T::slice_dims(self)
     }
 }
 
@@ -113,30 +248,37 @@ impl<T: Literal> Literal for Vec<T> {
     fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
         self.as_slice().write_literal(w, depth)
     }
+
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        T::slice_dims(self)
+    }
 }
 
 impl<T: Literal, const N: usize> Literal for [T; N] {
     fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
         self.as_slice().write_literal(w, depth)
     }
+
+    #[inline]
+    fn array_dims(&self) -> Option<Vec<usize>> {
+        T::slice_dims(self)
+    }
 }
 
+/// Tuples are written as composite values, `ROW(a, b)` at the top level or `(a,b)` within arrays
 macro_rules! impl_literal {
     ($(($($t:ident),*)),*) => {
         $(
-            impl<$($t: private::Sealed),*> private::Sealed for ($($t,)*) {}
             impl<$($t: Literal),*> Literal for ($($t,)*) {
                 #[allow(non_snake_case)]
-                fn write_literal(&self, mut w: &mut dyn Write, depth: usize) -> fmt::Result {
-                    if depth == 0 {
-                        w.write_str("'")?;
-                    }
+                fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+                    let ($($t,)*) = self;
 
-                    w.write_str("{")?;
+                    if depth == 0 {
+                        w.write_str("ROW(")?;
 
-                    {
                         let mut __thorn_inc = 0;
-                        let ($($t,)*) = self;
 
                         $(
                             if __thorn_inc > 0 {
@@ -144,16 +286,26 @@ macro_rules! impl_literal {
                             }
                             __thorn_inc += 1;
 
-                            $t.write_literal(&mut w, depth + 1)?;
+                            $t.write_literal(w, 0)?;
                         )*
-                    }
 
-                    w.write_str("}")?;
-                    if depth == 0 {
-                        w.write_str("'")?;
+                        return w.write_str(")");
                     }
 
-                    Ok(())
+                    let mut buf = String::from("(");
+                    let mut __thorn_inc = 0;
+
+                    $(
+                        if __thorn_inc > 0 {
+                            buf.push(',');
+                        }
+                        __thorn_inc += 1;
+
+                        write_field(&mut buf, $t, depth)?;
+                    )*
+
+                    buf.push(')');
+                    escape::write_array_element(&buf, w)
                 }
             }
         )*
@@ -177,36 +329,358 @@ impl_literal! {
     (A, B, C, D, E, F, G, H, I, J, K, L, M, N)
 }
 
-#[rustfmt::skip]
-fn escape_string(string: &str) -> String {
-    let mut out = String::with_capacity(string.len());
+/// Used by `#[derive(Literal)]` to write enums as `'variant'::"schema"."enum_type"`
+#[doc(hidden)]
+pub fn __write_enum<E: EnumType>(value: &E, w: &mut dyn Write, depth: usize) -> fmt::Result {
+    write_typed(w, depth, value.name(), &E::full_name())
+}
+
+/// Write a value from its text representation, as a string constant cast to `ty` at the top level
+fn write_typed(w: &mut dyn Write, depth: usize, text: &str, ty: &str) -> fmt::Result {
+    if depth > 0 {
+        return escape::write_array_element(text, w);
+    }
 
-    const FIND: &[char] =    &['\\',   '"',    '\'',  '\0',  '\x08', '\x09', '\x1a', '\n',  '\r'];
-    const REPLACE: &[&str] = &["\\\\", "\\\"", "\\'", "\\0", "\\b",  "\\t",  "\\z",  "\\n", "\\r"];
+    escape::write_string(text, &mut *w)?;
+    w.write_str("::")?;
+    w.write_str(ty)
+}
 
-    for c in string.chars() {
-        if let Some(i) = FIND.iter().position(|&f| f == c) {
-            out.push_str(REPLACE[i]);
-        } else {
-            out.push(c);
+/// Implement [`Literal`] for types whose [`Display`](fmt::Display) output is accepted by PostgreSQL
+macro_rules! impl_typed_lits {
+    ($feature:literal: $($ty:ty => $cast:literal),* $(,)?) => {$(
+        #[cfg(feature = $feature)]
+        impl Literal for $ty {
+            fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+                write_typed(w, depth, &self.to_string(), $cast)
+            }
         }
+    )*};
+}
+
+impl_typed_lits! {
+    "chrono":
+    chrono::NaiveDate => "date",
+    chrono::NaiveTime => "time",
+    chrono::NaiveDateTime => "timestamp",
+}
+
+impl_typed_lits! {
+    "time":
+    time::Date => "date",
+    time::Time => "time",
+    time::PrimitiveDateTime => "timestamp",
+}
+
+impl_typed_lits! {
+    "uuid":
+    uuid::Uuid => "uuid",
+}
+
+impl_typed_lits! {
+    "serde_json":
+    serde_json::Value => "jsonb",
+}
+
+impl_typed_lits! {
+    "rust_decimal":
+    rust_decimal::Decimal => "numeric",
+}
+
+impl_typed_lits! {
+    "ipnetwork":
+    ipnetwork::IpNetwork => "inet",
+    ipnetwork::Ipv4Network => "inet",
+    ipnetwork::Ipv6Network => "inet",
+}
+
+impl_typed_lits! {
+    "cidr":
+    cidr::IpCidr => "cidr",
+    cidr::IpInet => "inet",
+    cidr::Ipv4Cidr => "cidr",
+    cidr::Ipv4Inet => "inet",
+    cidr::Ipv6Cidr => "cidr",
+    cidr::Ipv6Inet => "inet",
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> Literal for chrono::DateTime<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        write_typed(w, depth, &self.to_rfc3339(), "timestamptz")
+    }
+}
+
+#[cfg(feature = "time")]
+impl Literal for time::OffsetDateTime {
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        let offset = self.offset();
+        let (h, m, s) = offset.as_hms();
+        let sign = if offset.is_negative() { '-' } else { '+' };
+
+        let text = format!(
+            "{} {}{sign}{:02}:{:02}:{:02}",
+            self.date(),
+            self.time(),
+            h.unsigned_abs(),
+            m.unsigned_abs(),
+            s.unsigned_abs()
+        );
+
+        write_typed(w, depth, &text, "timestamptz")
+    }
+}
+
+/// Write a field of a composite value nested in an array or a range bound, where `NULL` is an empty field
+/// and values containing special characters are quoted
+pub(crate) fn write_field(buf: &mut String, lit: &impl Literal, depth: usize) -> fmt::Result {
+    let mut field = String::new();
+    lit.write_literal(&mut field, depth + 1)?;
+
+    if field == "NULL" {
+        return Ok(());
     }
 
-    out
+    if field.starts_with('"') || !field.contains(|c: char| "()[],\"\\{} ".contains(c) || c.is_whitespace()) {
+        buf.push_str(&field);
+        return Ok(());
+    }
+
+    escape::write_array_element(&field, buf)
 }
 
-pub(crate) fn write_escaped_string_quoted(string: &str, mut w: impl Write) -> fmt::Result {
-    let escaped = escape_string(string);
+/// Write an array literal directly when nested in another, or as a string constant at the top level
+fn write_nested(w: &mut dyn Write, depth: usize, f: impl FnOnce(&mut dyn Write) -> fmt::Result) -> fmt::Result {
+    if depth > 0 {
+        return f(w);
+    }
 
-    w.write_str(if escaped.find('\\').is_some() { "E'" } else { "'" })?;
-    w.write_str(&escaped)?;
-    w.write_str("'")
+    let mut buf = String::new();
+    f(&mut buf)?;
+    escape::write_string(&buf, w)
 }
 
-fn write_escaped_string_nested(string: &str, mut w: impl Write) -> fmt::Result {
-    let escaped = escape_string(string);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(value: impl Literal) -> Result<String, fmt::Error> {
+        let mut buf = String::new();
+        value.write_literal(&mut buf, 0)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(lit(u16::MAX).unwrap(), "65535");
+        assert_eq!(lit(u32::MAX).unwrap(), "4294967295");
+        assert_eq!(lit(u64::MAX >> 1).unwrap(), "9223372036854775807");
+        assert!(lit(u64::MAX).is_err());
+        assert_eq!(lit(i128::MIN).unwrap(), "-170141183460469231731687303715884105728");
+        assert_eq!(lit('\'').unwrap(), "''''");
+        assert_eq!(lit(Some(Box::new(1u8))).unwrap(), "1");
+        assert_eq!(lit(vec![Some(1), None]).unwrap(), "'{1, NULL}'");
+        assert_eq!(lit(Cow::Borrowed("a")).unwrap(), "'a'");
+        assert_eq!(lit(b"\x00\xff").unwrap(), r"E'\\x00ff'");
+        assert_eq!(lit(vec![vec![1u8]]).unwrap(), r#"E'{"\\\\x01"}'"#);
+        assert_eq!(lit(f64::NAN).unwrap(), "'NaN'::float8");
+        assert_eq!(lit(f32::NEG_INFINITY).unwrap(), "'-Infinity'::float4");
+        assert_eq!(lit([1.5, f64::INFINITY]).unwrap(), "'{1.5, Infinity}'");
+    }
+
+    #[test]
+    fn test_composite_and_array_literals() {
+        assert_eq!(lit((1, "it's", None::<i32>)).unwrap(), "ROW(1, 'it''s', NULL)");
+        assert_eq!(lit([(1, "a b", None::<i32>)]).unwrap(), r#"E'{"(1,\\"a b\\",)"}'"#);
+        assert_eq!(
+            lit(vec![vec![Some(1), None], vec![Some(3), Some(4)]]).unwrap(),
+            "'{{1, NULL}, {3, 4}}'"
+        );
+        assert_eq!(lit(vec![Some("NULL"), None]).unwrap(), r#"'{"NULL", NULL}'"#);
+
+        assert!(lit(vec![vec![1], vec![2, 3]]).is_err());
+        assert!(lit(vec![vec![vec![1]], vec![vec![2], vec![3]]]).is_err());
+        assert_eq!(
+            lit(vec![b"ab".to_vec(), b"c".to_vec()]).unwrap(),
+            r#"E'{"\\\\x6162", "\\\\x63"}'"#
+        );
+    }
+
+    #[derive(Literal)]
+    #[thorn(crate = crate)]
+    struct UserId(i64);
+
+    #[derive(Literal)]
+    #[thorn(crate = crate)]
+    struct Wrapper<T> {
+        inner: T,
+    }
+
+    crate::enums! {
+        #[derive(Literal)]
+        #[thorn(crate = crate)]
+        pub enum Mood in Demo { Happy, Sad }
+    }
+
+    #[test]
+    fn test_derive_literal() {
+        assert_eq!(lit(UserId(1)).unwrap(), "1");
+        assert_eq!(lit(Wrapper { inner: "it's" }).unwrap(), "'it''s'");
+        assert_eq!(lit(vec![Wrapper { inner: UserId(1) }]).unwrap(), "'{1}'");
+        assert_eq!(lit(Mood::Happy).unwrap(), r#"'happy'::"demo"."mood""#);
+        assert_eq!(
+            lit((UserId(2), [Mood::Happy, Mood::Sad])).unwrap(),
+            r#"ROW(2, '{"happy", "sad"}')"#
+        );
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid", feature = "serde_json"))]
+    #[test]
+    fn test_typed_literals() {
+        use crate::ty::TypeExt;
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let json = serde_json::json!({"it's": [1, null]});
+
+        assert_eq!(lit(date).unwrap(), "'2024-02-29'::date");
+        assert_eq!(
+            lit(uuid::Uuid::nil()).unwrap(),
+            "'00000000-0000-0000-0000-000000000000'::uuid"
+        );
+        assert_eq!(lit(&json).unwrap(), r#"'{"it''s":[1,null]}'::jsonb"#);
+        assert_eq!(lit(vec![date]).unwrap(), r#"'{"2024-02-29"}'"#);
+
+        assert_eq!(pg::Type::UUID_ARRAY.rust_type().as_deref(), Some("Vec<uuid::Uuid>"));
+        assert_eq!(pg::Type::JSONB.rust_type().as_deref(), Some("serde_json::Value"));
+    }
+
+    /// Characters that are significant to string, array or identifier syntax
+    const CHARS: &[char] = &[
+        '\'', '"', '\\', '{', '}', ',', ' ', '\n', '\t', '$', 'E', 'x', 'a', 'é', '🦀',
+    ];
+
+    fn random_string(seed: &mut u64) -> String {
+        let mut next = || {
+            // xorshift64
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            *seed as usize
+        };
+
+        let len = next() % 12;
+        (0..len).map(|_| CHARS[next() % CHARS.len()]).collect()
+    }
+
+    /// Parses escaped literals back through PostgreSQL, set `THORN_TEST_DB` to a connection string to run
+    #[cfg(feature = "extensions")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_literal_round_trip() {
+        let db = std::env::var("THORN_TEST_DB").expect("THORN_TEST_DB is not set");
+        let (client, conn) = pgt::connect(&db, pgt::NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        let mut seed = 0x2545_F491_4F6C_DD1D;
+
+        for conforming in ["on", "off"] {
+            client.batch_execute(&format!("SET standard_conforming_strings = {conforming}")).await.unwrap();
 
-    w.write_str("\"")?;
-    w.write_str(&escaped)?;
-    w.write_str("\"")
+            for _ in 0..200 {
+                let value = random_string(&mut seed);
+                let values = vec![random_string(&mut seed), random_string(&mut seed)];
+                let bytes = value.as_bytes();
+
+                let (mut lit, mut array, mut bytea, mut ident) = <[String; 4]>::default().into();
+                value.write_literal(&mut lit, 0).unwrap();
+                values.write_literal(&mut array, 0).unwrap();
+                crate::escape::write_bytea(bytes, &mut bytea).unwrap();
+                crate::escape::write_identifier(&format!("_{value}"), &mut ident).unwrap();
+
+                let sql = format!("SELECT {lit}::text, {array}::text[], {bytea}::bytea, 1 AS {ident}");
+                let row = client.query_one(&sql, &[]).await.unwrap_or_else(|e| panic!("{sql}: {e}"));
+
+                assert_eq!(row.get::<_, String>(0), value, "{sql}");
+                assert_eq!(row.get::<_, Vec<String>>(1), values, "{sql}");
+                assert_eq!(row.get::<_, Vec<u8>>(2), bytes, "{sql}");
+
+                assert_eq!(row.columns()[3].name(), format!("_{value}"), "{sql}");
+            }
+
+            let (floats, blobs) = ([f64::NAN, f64::NEG_INFINITY, 0.5], vec![vec![0u8, 255], vec![]]);
+            let sql = format!(
+                "SELECT {}, {}::float8[], {}::bytea[]",
+                lit(f32::INFINITY).unwrap(),
+                lit(floats).unwrap(),
+                lit(&blobs).unwrap()
+            );
+            let row = client.query_one(&sql, &[]).await.unwrap_or_else(|e| panic!("{sql}: {e}"));
+
+            assert_eq!(row.get::<_, f32>(0), f32::INFINITY);
+            assert_eq!(format!("{:?}", row.get::<_, Vec<f64>>(1)), format!("{floats:?}"));
+            assert_eq!(row.get::<_, Vec<Vec<u8>>>(2), blobs);
+        }
+
+        // typed literals from optional features
+        let mut lits: Vec<(String, &str)> = Vec::new();
+
+        #[cfg(feature = "chrono")]
+        lits.push((
+            lit(chrono::DateTime::parse_from_rfc3339("2024-02-29T12:30:00.5-03:30").unwrap()).unwrap(),
+            "2024-02-29 16:00:00.5+00",
+        ));
+        #[cfg(feature = "time")]
+        lits.push({
+            let date = time::Date::from_calendar_date(2024, time::Month::February, 29).unwrap();
+            let offset = time::UtcOffset::from_hms(-3, -30, 0).unwrap();
+            let value = date.with_hms_milli(12, 30, 0, 500).unwrap().assume_offset(offset);
+
+            (lit(value).unwrap(), "2024-02-29 16:00:00.5+00")
+        });
+        #[cfg(feature = "rust_decimal")]
+        lits.push((lit(rust_decimal::Decimal::new(-12345, 3)).unwrap(), "-12.345"));
+        #[cfg(feature = "ipnetwork")]
+        lits.push((
+            lit("10.1.0.0/16".parse::<ipnetwork::IpNetwork>().unwrap()).unwrap(),
+            "10.1.0.0/16",
+        ));
+        #[cfg(feature = "cidr")]
+        lits.push((
+            lit("2001:db8::/32".parse::<cidr::IpCidr>().unwrap()).unwrap(),
+            "2001:db8::/32",
+        ));
+
+        // composite values and multidimensional arrays
+        client.batch_execute("CREATE TYPE pg_temp.pair AS (a int4, b text, c int4[])").await.unwrap();
+
+        let pair = (1, r#"a "b" \c"#, vec![Some(2), None]);
+        let grid = vec![vec![Some(1), None], vec![Some(3), Some(4)]];
+
+        let sql = format!(
+            "SELECT ({})::pg_temp.pair::text, {}::pg_temp.pair[]::text, {}::int4[]::text",
+            lit(&pair).unwrap(),
+            lit([&pair, &pair]).unwrap(),
+            lit(&grid).unwrap()
+        );
+        let row = client.query_one(&sql, &[]).await.unwrap_or_else(|e| panic!("{sql}: {e}"));
+
+        assert_eq!(row.get::<_, String>(0), r#"(1,"a ""b"" \\c","{2,NULL}")"#);
+        assert_eq!(
+            row.get::<_, String>(1).matches(r#"a \"\"b\"\" \\\\c"#).count(),
+            2,
+            "{sql}"
+        );
+        assert_eq!(row.get::<_, String>(2), "{{1,NULL},{3,4}}");
+
+        client.batch_execute("SET TIME ZONE 'UTC'").await.unwrap();
+
+        for (lit, expected) in lits {
+            let sql = format!("SELECT {lit}::text");
+            let row = client.query_one(&sql, &[]).await.unwrap_or_else(|e| panic!("{sql}: {e}"));
+            assert_eq!(row.get::<_, String>(0), expected, "{sql}");
+        }
+    }
 }
@@ -16,6 +16,24 @@ pub trait RowColumns: std::any::Any + From<pgt::Row> {}
 
 impl<T> RowColumns for T where T: std::any::Any + From<pgt::Row> {}
 
+/// Location of the `sql!` invocation that produced a query
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.file.is_empty() {
+            return f.write_str("<unknown>");
+        }
+
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
 pub struct Query<'a, E: RowColumns> {
     /// The query string
     pub q: String,
@@ -26,14 +44,57 @@ pub struct Query<'a, E: RowColumns> {
     /// The parameters to the query
     pub params: Vec<&'a (dyn pg::ToSql + Sync + 'a)>,
 
+    /// Which parameters are bound to `#[sensitive]` columns
+    pub param_sensitive: Vec<bool>,
+
     /// Reference to a cached static query
     pub cached: Option<&'static StaticQuery<E>>,
+
+    /// Where the query was defined
+    pub location: SourceLocation,
+
+    /// Maps text in the query string back to the `sql!` tokens that produced it
+    pub source_map: SourceMap,
 }
 
+/// Whether a parameter type is a `#[sensitive]` column, using autoref specialization
+/// so any other `Into<pg::Type>` is accepted as well.
+#[doc(hidden)]
+pub struct Sensitivity<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ColumnSensitivity {
+    fn __is_sensitive(&self) -> bool;
+}
+
+impl<C: Column> ColumnSensitivity for Sensitivity<'_, C> {
+    #[inline(always)]
+    fn __is_sensitive(&self) -> bool {
+        self.0.sensitive()
+    }
+}
+
+#[doc(hidden)]
+pub trait TypeSensitivity {
+    #[inline(always)]
+    fn __is_sensitive(&self) -> bool {
+        false
+    }
+}
+
+impl<T> TypeSensitivity for &Sensitivity<'_, T> {}
+
+/// Placeholder parameter value used when building the text of static queries
+#[doc(hidden)]
+pub static NULL_PARAM: Option<i32> = None;
+
 #[doc(hidden)]
 pub struct StaticQuery<E: RowColumns> {
     pub q: String,
     pub params: Vec<pg::Type>,
+    pub param_sensitive: Vec<bool>,
+    pub location: SourceLocation,
+    pub source_map: SourceMap,
     e: PhantomData<E>,
 }
 
@@ -43,6 +104,9 @@ impl<E: RowColumns> From<Query<'_, E>> for StaticQuery<E> {
         StaticQuery {
             q: q.q,
             params: q.param_tys,
+            param_sensitive: q.param_sensitive,
+            location: q.location,
+            source_map: q.source_map,
             e: PhantomData,
         }
     }
@@ -54,15 +118,22 @@ impl<E: RowColumns> Default for Query<'_, E> {
             q: String::with_capacity(128),
             params: Default::default(),
             param_tys: Default::default(),
+            param_sensitive: Default::default(),
             cached: None,
+            location: SourceLocation::default(),
+            source_map: SourceMap::default(),
         }
     }
 }
 
 use crate::{
+    debug_sql::Redaction,
+    escape,
     func::Func,
     literal::Literal,
     name::Schema,
+    source_map::{SourceMap, SourceMapTable},
+    spacing::needs_space,
     table::{Column, Table, TableExt},
 };
 use std::{
@@ -70,28 +141,25 @@ use std::{
     fmt::{self, Write},
 };
 
-use crate::literal::write_escaped_string_quoted;
-
-#[allow(clippy::single_char_add_str)]
 impl<E: RowColumns> Write for Query<'_, E> {
     #[inline(always)]
     fn write_str(&mut self, s: &str) -> fmt::Result {
+        let start = self.q.len();
         self.q.push_str(s);
-        self.q.push_str(" ");
+        self.finish_write(start);
         Ok(())
     }
 
     #[inline(always)]
     fn write_char(&mut self, c: char) -> fmt::Result {
-        self.q.push(c);
-        self.q.push_str(" ");
-        Ok(())
+        Write::write_str(self, c.encode_utf8(&mut [0; 4]))
     }
 
     #[inline(always)]
     fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        let start = self.q.len();
         self.q.write_fmt(args)?;
-        self.q.push_str(" ");
+        self.finish_write(start);
         Ok(())
     }
 }
@@ -103,32 +171,152 @@ impl<'a, E: RowColumns> Query<'a, E> {
         &mut self.q
     }
 
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __new(location: SourceLocation, source_map: &'static SourceMapTable) -> Self {
+        Query {
+            location,
+            source_map: SourceMap::new(source_map),
+            ..Query::default()
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __source(&mut self, chunk: u32) {
+        self.source_map.__mark(chunk);
+    }
+
+    /// Separates the text written since `start` from the preceding token, if needed,
+    /// and maps it to the current source chunk.
+    fn finish_write(&mut self, start: usize) {
+        if start == self.q.len() {
+            return;
+        }
+
+        let mut start = start;
+
+        if needs_space(&self.q[..start], &self.q[start..]) {
+            self.q.insert(start, ' ');
+            start += 1;
+        }
+
+        self.source_map.__written(start);
+    }
+
+    /// The query string, from the cached static query if there is one
+    #[inline]
+    pub fn sql(&self) -> &str {
+        match self.cached {
+            Some(cached) => &cached.q,
+            None => &self.q,
+        }
+    }
+
+    /// The types of the parameters, from the cached static query if there is one
+    #[inline]
+    pub fn param_types(&self) -> &[pg::Type] {
+        match self.cached {
+            Some(cached) => &cached.params,
+            None => &self.param_tys,
+        }
+    }
+
+    /// Which parameters are bound to `#[sensitive]` columns, from the cached static query if there is one
+    #[inline]
+    pub fn param_sensitivity(&self) -> &[bool] {
+        match self.cached {
+            Some(cached) => &cached.param_sensitive,
+            None => &self.param_sensitive,
+        }
+    }
+
+    /// The source map of the query, from the cached static query if there is one
+    #[inline]
+    pub fn source_map(&self) -> &SourceMap {
+        match self.cached {
+            Some(cached) => &cached.source_map,
+            None => &self.source_map,
+        }
+    }
+
+    /// Format the query with each clause on its own line and subqueries indented, for human reading.
+    ///
+    /// The query itself is always rendered with minimal whitespace, see [`pretty`](crate::pretty::pretty).
+    pub fn pretty(&self) -> String {
+        crate::pretty::pretty(self.sql())
+    }
+
+    /// Render the query with each parameter inlined as a literal cast to its type,
+    /// so it can be pasted into `psql` and run.
+    ///
+    /// This includes the values of all parameters, see [`to_debug_sql_redacted`](Self::to_debug_sql_redacted)
+    /// for output that is safe to log.
+    pub fn to_debug_sql(&self) -> String {
+        self.to_debug_sql_redacted(&Redaction::none())
+    }
+
+    /// Like [`to_debug_sql`](Self::to_debug_sql), but masks parameters according to `redaction`.
+    ///
+    /// ```ignore
+    /// log::debug!("{}", query.to_debug_sql_redacted(&Redaction::sensitive().with_type(Type::BYTEA)));
+    /// ```
+    pub fn to_debug_sql_redacted(&self, redaction: &Redaction) -> String {
+        crate::debug_sql::render(
+            self.sql(),
+            &self.params,
+            self.param_types(),
+            self.param_sensitivity(),
+            redaction,
+        )
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     pub fn __from_cached(cached: &'static StaticQuery<E>, params: Vec<&'a (dyn pg::ToSql + Sync)>) -> Self {
         Query {
             params,
             cached: Some(cached),
+            location: cached.location,
 
-            // these two don't need to allocate
+            // these don't need to allocate
             q: String::new(),
             param_tys: Vec::new(),
+            param_sensitive: Vec::new(),
+            source_map: SourceMap::default(),
         }
     }
 
     pub fn param<const DYNAMIC: bool>(
+        &mut self,
+        value: &'a (dyn pg::ToSql + Sync),
+        ty: impl Into<pg::Type>,
+    ) -> Result<(), SqlFormatError> {
+        self.__param::<DYNAMIC>(value, ty.into(), false)
+    }
+
+    /// Like [`param`](Self::param), but marks the parameter as sensitive for [`to_debug_sql_redacted`](Self::to_debug_sql_redacted)
+    #[doc(hidden)]
+    pub fn __param<const DYNAMIC: bool>(
         &mut self,
         value: &'a (dyn pg::ToSql + Sync),
         ty: pg::Type,
+        sensitive: bool,
     ) -> Result<(), SqlFormatError> {
-        let idx = match self.params.iter().position(|&p| {
-            // SAFETY: Worst-case parameter duplication, best-case using codegen-units=1 no issues at all
-            std::ptr::eq(
-                p as *const (dyn pg::ToSql + Sync),
-                value as *const (dyn pg::ToSql + Sync),
-            )
-        }) {
-            Some(idx) if DYNAMIC => {
+        // static queries are built with placeholder values, so only dynamic queries can be deduplicated
+        let existing = match DYNAMIC {
+            true => self.params.iter().position(|&p| {
+                // SAFETY: Worst-case parameter duplication, best-case using codegen-units=1 no issues at all
+                std::ptr::eq(
+                    p as *const (dyn pg::ToSql + Sync),
+                    value as *const (dyn pg::ToSql + Sync),
+                )
+            }),
+            false => None,
+        };
+
+        let idx = match existing {
+            Some(idx) => {
                 if ty != pg::Type::ANY {
                     let existing_ty = &self.param_tys[idx];
                     if *existing_ty == pg::Type::ANY {
@@ -138,60 +326,98 @@ impl<'a, E: RowColumns> Query<'a, E> {
                     }
                 }
 
+                self.param_sensitive[idx] |= sensitive;
+
                 idx + 1 // 1-indexed
             }
-            v => {
-                if !DYNAMIC && v.is_some() {
-                    eprintln!("Duplicate {ty} parameter at index {}", v.unwrap());
-                }
-
+            None => {
                 self.param_tys.push(ty);
+                self.param_sensitive.push(sensitive);
                 self.params.push(value);
                 self.params.len() // 1-indexed, take len after push
             }
         };
 
-        self.inner().push_str("$");
-        self.write_literal(idx as i64).map_err(From::from)
+        let start = self.q.len();
+        self.q.push_str("$");
+        self.q.push_str(itoa::Buffer::new().format(idx));
+        self.finish_write(start);
+
+        Ok(())
     }
 
     #[inline(always)]
     pub fn write_literal<L: Literal>(&mut self, lit: L) -> fmt::Result {
-        lit.write_literal(self.inner(), 0)
+        let start = self.q.len();
+        lit.write_literal(&mut self.q, 0)?;
+        self.finish_write(start);
+        Ok(())
     }
 
     #[inline(always)]
     pub fn write_column<T: TableExt>(&mut self, col: T, name: &'static str) -> fmt::Result {
-        write!(
-            self.inner(),
-            "\"{}\".\"{}\"",
-            if name == T::TYPENAME_SNAKE { <T as Table>::NAME.name() } else { name },
-            <T as Column>::name(&col)
-        )
+        let table = if name == T::TYPENAME_SNAKE { <T as Table>::NAME.name() } else { name };
+
+        self.write_identifiers(&[table, <T as Column>::name(&col)])
     }
 
     #[inline(always)]
     pub fn write_str(&mut self, s: &str) {
-        self.inner().push_str(s)
+        let _ = Write::write_str(self, s);
     }
 
     #[inline]
     pub fn write_table<T: Table>(&mut self) -> fmt::Result {
-        match (T::ALIAS, T::SCHEMA) {
-            (None, Schema::None) => write!(self, "\"{}\"", T::NAME.name()),
-            (None, Schema::Named(name)) => write!(self, "\"{}\".\"{}\"", name, T::NAME.name()),
-            (Some(alias), Schema::None) => write!(self, "\"{}\" AS {alias}", T::NAME.name()),
-            (Some(alias), Schema::Named(name)) => write!(self, "\"{}\".\"{}\" AS {alias}", name, T::NAME.name()),
+        match T::SCHEMA {
+            Schema::None => self.write_identifiers(&[T::NAME.name()])?,
+            Schema::Named(schema) => self.write_identifiers(&[schema, T::NAME.name()])?,
         }
+
+        if let Some(alias) = T::ALIAS {
+            self.write_str("AS");
+            self.write_identifiers(&[alias])?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a column chosen at runtime, such as one parsed from user input, as a qualified identifier.
+    ///
+    /// `scope` lists the tables in scope as `(TableExt::TYPENAME_SNAKE, name in the query)` pairs,
+    /// innermost first, so a column of a table renamed with `AS` is qualified with its alias.
+    #[inline]
+    pub fn write_column_ident<T: TableExt>(&mut self, col: T, scope: &[(&str, &'static str)]) -> fmt::Result {
+        let name = match scope.iter().find(|(table, _)| *table == T::TYPENAME_SNAKE) {
+            Some((_, name)) => name,
+            None => T::TYPENAME_SNAKE,
+        };
+
+        self.write_column(col, name)
     }
 
     pub fn write_column_name<C: Column>(&mut self, col: C) -> fmt::Result {
-        write!(self.inner(), "\"{}\" ", col.name())
+        self.write_identifiers(&[col.name()])
+    }
+
+    /// Write a dot-separated path of quoted identifiers as a single token
+    fn write_identifiers(&mut self, names: &[&str]) -> fmt::Result {
+        let start = self.q.len();
+
+        for (i, name) in names.iter().enumerate() {
+            if i > 0 {
+                self.q.push('.');
+            }
+
+            escape::write_identifier(name, &mut self.q)?;
+        }
+
+        self.finish_write(start);
+        Ok(())
     }
 
     #[inline(always)]
     pub fn write_func<F: Func>(&mut self) {
-        self.inner().push_str(F::NAME)
+        self.write_str(F::NAME)
     }
 }
 
@@ -208,12 +434,26 @@ impl<'a, E: RowColumns> Query<'a, E> {
 /// * Non-keyword identifiers are treated as [`Table`](crate::Table) types.
 /// * `Ident::Ident` is treated as a column, so `TestTable::Col` converts to `"test_table"."col"`
 ///     * `AS Ident::Ident` is treated specially to remove all but the column name for alises.
-/// * Arbitrary expressions are allowed with code-blocks `{let x = 10; x + 21}`, but will be converted to [`Literal`](crate::Literal) values.
+/// * Arbitrary expressions are allowed with code-blocks `{let x = 10; x + 21}`, but will be converted to [`Literal`](crate::literal::Literal) values.
+///     * `None` is written as `NULL`, byte slices as `bytea`, tuples as `ROW(...)` and non-finite floats as `'NaN'::float8`.
+///       Values with no SQL representation, such as a `u64` larger than `i64::MAX` or non-rectangular
+///       nested `Vec`s, fail to format.
 ///     * To escape this behavior, prefix the code block with `@`, so `@{"something weird"}` is added directly as `something weird`, not a string.
+///     * Blocks ending in `!`, such as `{sort!}`, write a column chosen at runtime as a quoted identifier,
+///       for columns parsed from user input with `"user_name".parse::<Users>()`. It is qualified with the
+///       innermost table of its type in scope, by alias if renamed with `AS`.
 /// * Parametric values can be specified with `#{1}` or `#{2 => Type::INT8}` for accumulating types
+///     * Parameters typed by a column, `#{&id as Users::Id}`, are checked against the Rust type of the column
+///       if one is declared in [`tables!`], such as `Id: Type::INT8 => i64`
 /// * For-loops in codegen are supported like `for your_variable in your_data { SELECT {your_variable} }
 /// * Conditionals are supported via `if condition { SELECT "true" }`
 ///     * Also supports an `else { SELECT "false" }` branch
+/// * Some likely mistakes are compile errors, which can be disabled with `#![allow(lint)]` at the start of the macro:
+///     * `missing_where`: `UPDATE` or `DELETE` without a `WHERE` clause
+///     * `column_count`: `INSERT INTO Table (Col, Col) VALUES (...)` with the wrong number of values
+///     * `missing_limit`: `SELECT ... FROM` without a `LIMIT` in queries marked with `#![single]`
+///     * `out_of_scope`: `Table.Column` where `Table` is not in a `FROM`, `JOIN`, `USING` or `UPDATE` clause
+///       of the query or an enclosing query
 #[macro_export]
 macro_rules! sql {
     ($($tt:tt)*) => {{
@@ -371,4 +611,33 @@ mod tests {
 
         println!("OUT: {}", res.q);
     }
+
+    #[test]
+    fn test_lint_attrs() {
+        let query = sql! {
+            #![single]
+            #![allow(missing_where)]
+
+            DELETE FROM TestTable;
+            SELECT TestTable.SomeCol FROM TestTable LIMIT 1
+        }
+        .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"DELETE FROM "my_schema"."renamed"; SELECT "renamed"."some_col" FROM "my_schema"."renamed" LIMIT 1"#
+        );
+    }
+
+    #[test]
+    fn test_escaped_literals() {
+        let values = vec![String::from("it's"), String::from(r#"a "b""#)];
+
+        let query = sql! { SELECT "it's", r"C:\path", b"\x00\x0a", {values} }.unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"SELECT 'it''s', E'C:\\path', E'\\x000a', E'{"it''s", "a \\"b\\""}'"#
+        );
+    }
 }
@@ -0,0 +1,184 @@
+//! Indented formatting of rendered queries for human reading.
+//!
+//! Each clause starts on a new line, and subqueries and CTEs are indented within their parentheses:
+//!
+//! ```text
+//! WITH "recent" AS (
+//!     SELECT "messages"."id"
+//!     FROM "messages"
+//!     WHERE "messages"."author" = $1
+//! )
+//! SELECT "recent"."id"
+//! FROM "recent"
+//! INNER JOIN "users" ON "users"."id" = "recent"."id"
+//! ```
+
+use crate::lexer;
+use crate::spacing::needs_space;
+
+const INDENT: &str = "    ";
+
+const JOIN_PREFIX: &[&str] = &["INNER", "LEFT", "RIGHT", "FULL", "CROSS", "NATURAL", "OUTER"];
+
+/// Format an SQL query with each clause on its own line and subqueries indented.
+///
+/// Only whitespace outside of literals, quoted identifiers and comments is changed.
+pub fn pretty(sql: &str) -> String {
+    let tokens: Vec<&str> = lexer::tokens(sql).map(|(_, token)| token).collect();
+
+    let mut out = String::with_capacity(sql.len() + sql.len() / 4);
+
+    // for each open parenthesis, whether it contains a subquery
+    let mut parens: Vec<bool> = Vec::new();
+
+    for (i, &token) in tokens.iter().enumerate() {
+        let depth = parens.iter().filter(|&&block| block).count();
+
+        match token {
+            "(" => {
+                let block = matches!(
+                    keyword(&tokens, i + 1).as_deref(),
+                    Some("SELECT" | "WITH" | "VALUES" | "INSERT" | "UPDATE" | "DELETE")
+                );
+
+                push(&mut out, token);
+                parens.push(block);
+
+                if block {
+                    newline(&mut out, depth + 1);
+                }
+            }
+            ")" => {
+                if parens.pop() == Some(true) {
+                    newline(&mut out, depth - 1);
+                }
+
+                push(&mut out, token);
+            }
+            _ => {
+                if !matches!(parens.last(), Some(false)) && is_clause(&tokens, i) {
+                    newline(&mut out, depth);
+                }
+
+                push(&mut out, token);
+
+                // line comments extend to the end of the line
+                if token.starts_with("--") {
+                    newline(&mut out, depth);
+                }
+            }
+        }
+    }
+
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn push(out: &mut String, token: &str) {
+    if needs_space(out, token) {
+        out.push(' ');
+    }
+
+    out.push_str(token);
+}
+
+/// Starts a new line at the given depth, unless the current line is already empty
+fn newline(out: &mut String, depth: usize) {
+    let line_start = out.rfind('\n').map_or(0, |i| i + 1);
+
+    if out[line_start..].trim().is_empty() {
+        out.truncate(line_start);
+    } else {
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+    }
+
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+fn keyword(tokens: &[&str], i: usize) -> Option<String> {
+    tokens.get(i).map(|t| t.to_ascii_uppercase())
+}
+
+/// Whether the token at `i` starts a new clause
+fn is_clause(tokens: &[&str], i: usize) -> bool {
+    let Some(kw) = keyword(tokens, i) else { return false };
+
+    let prev = i.checked_sub(1).and_then(|i| keyword(tokens, i));
+    let next = keyword(tokens, i + 1);
+
+    let (prev, next) = (prev.as_deref().unwrap_or(""), next.as_deref().unwrap_or(""));
+
+    match kw.as_str() {
+        "SELECT" | "WHERE" | "HAVING" | "LIMIT" | "OFFSET" | "RETURNING" | "VALUES" | "WINDOW" | "UNION"
+        | "INTERSECT" | "EXCEPT" | "INSERT" | "SET" => true,
+
+        "WITH" => !matches!(next, "ORDINALITY" | "TIME" | "NO" | "CHECK" | "GRANT" | "HOLD"),
+        "FROM" => !matches!(prev, "DELETE" | "DISTINCT"),
+        "UPDATE" => !matches!(prev, "DO" | "FOR" | "ON" | "NO" | "KEY"),
+        "DELETE" => prev != "ON",
+        "GROUP" | "ORDER" => next == "BY",
+        "ON" => next == "CONFLICT",
+        "JOIN" => !JOIN_PREFIX.contains(&prev),
+
+        _ if JOIN_PREFIX.contains(&kw.as_str()) && !JOIN_PREFIX.contains(&prev) => {
+            let mut rest = (i + 1..tokens.len()).filter_map(|j| keyword(tokens, j));
+            rest.find(|t| !JOIN_PREFIX.contains(&t.as_str())).as_deref() == Some("JOIN")
+        }
+
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pg::Type;
+    use crate::table::TestTable;
+
+    crate::tables! {
+        pub struct Recent {
+            Id: Type::INT8,
+        }
+    }
+
+    #[test]
+    fn test_pretty() {
+        let ids = vec![1i64];
+        let query = sql! {
+            WITH Recent AS (SELECT TestTable.Id FROM TestTable WHERE TestTable.Id = ANY(#{&ids as Type::INT8_ARRAY}))
+            SELECT TestTable.Id, count(*) FROM TestTable INNER JOIN Recent ON TRUE
+            WHERE TestTable.UserName = "x" GROUP BY TestTable.Id
+        }
+        .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"WITH "recent" AS (SELECT "tt"."id" FROM "test_schema"."tt" WHERE "tt"."id" = ANY ($1)) SELECT "tt"."id", count(*) FROM "test_schema"."tt" INNER JOIN "recent" ON TRUE WHERE "tt"."user_name" = 'x' GROUP BY "tt"."id""#
+        );
+
+        assert_eq!(
+            query.pretty(),
+            r#"WITH "recent" AS (
+    SELECT "tt"."id"
+    FROM "test_schema"."tt"
+    WHERE "tt"."id" = ANY ($1)
+)
+SELECT "tt"."id", count(*)
+FROM "test_schema"."tt"
+INNER JOIN "recent" ON TRUE
+WHERE "tt"."user_name" = 'x'
+GROUP BY "tt"."id""#
+        );
+    }
+    #[test]
+    fn test_cast_spacing() {
+        let ty = Type::INT8;
+        let dynamic = sql! { SELECT 1::{ty}, 2::{Type::TEXT} }.unwrap();
+        let fixed = sql! { SELECT 1::int8, 2::text }.unwrap();
+
+        assert_eq!(dynamic.sql(), "SELECT 1::int8, 2::text");
+        assert_eq!(dynamic.sql(), fixed.sql());
+    }
+}
@@ -0,0 +1,419 @@
+//! Range and multirange types, such as `int8range`, `tstzrange` and `datemultirange`.
+//!
+//! [`Range`] and [`Multirange`] can be used as parameters, read from rows and written as literals:
+//!
+//! ```ignore
+//! use thorn::range::Range;
+//!
+//! let during: Range<i64> = (10..20).into();
+//!
+//! sql! { SELECT Slots.Id FROM Slots WHERE Slots.During && #{&during as Slots::During} };
+//! ```
+
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::ops::{self, Bound};
+
+use pg::private::BytesMut;
+use pg::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type};
+
+use crate::escape;
+use crate::literal::{write_field, Literal};
+
+// flags of the binary format, see `src/include/utils/rangetypes.h`
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LB_INC: u8 = 0x02;
+const RANGE_UB_INC: u8 = 0x04;
+const RANGE_LB_INF: u8 = 0x08;
+const RANGE_UB_INF: u8 = 0x10;
+
+/// A PostgreSQL range with inclusive, exclusive or unbounded bounds, or the empty range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Range<T> {
+    Empty,
+    Nonempty(Bound<T>, Bound<T>),
+}
+
+impl<T> Range<T> {
+    pub const fn new(lower: Bound<T>, upper: Bound<T>) -> Self {
+        Range::Nonempty(lower, upper)
+    }
+
+    pub const fn empty() -> Self {
+        Range::Empty
+    }
+
+    /// The range containing every value, `(,)`
+    pub const fn unbounded() -> Self {
+        Range::Nonempty(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Range::Empty)
+    }
+
+    pub fn lower(&self) -> Bound<&T> {
+        match self {
+            Range::Empty => Bound::Unbounded,
+            Range::Nonempty(lower, _) => lower.as_ref(),
+        }
+    }
+
+    pub fn upper(&self) -> Bound<&T> {
+        match self {
+            Range::Empty => Bound::Unbounded,
+            Range::Nonempty(_, upper) => upper.as_ref(),
+        }
+    }
+}
+
+impl<T> From<ops::Range<T>> for Range<T> {
+    fn from(range: ops::Range<T>) -> Self {
+        Range::new(Bound::Included(range.start), Bound::Excluded(range.end))
+    }
+}
+
+impl<T> From<ops::RangeInclusive<T>> for Range<T> {
+    fn from(range: ops::RangeInclusive<T>) -> Self {
+        let (start, end) = range.into_inner();
+        Range::new(Bound::Included(start), Bound::Included(end))
+    }
+}
+
+impl<T> From<ops::RangeFrom<T>> for Range<T> {
+    fn from(range: ops::RangeFrom<T>) -> Self {
+        Range::new(Bound::Included(range.start), Bound::Unbounded)
+    }
+}
+
+impl<T> From<ops::RangeTo<T>> for Range<T> {
+    fn from(range: ops::RangeTo<T>) -> Self {
+        Range::new(Bound::Unbounded, Bound::Excluded(range.end))
+    }
+}
+
+impl<T> From<ops::RangeToInclusive<T>> for Range<T> {
+    fn from(range: ops::RangeToInclusive<T>) -> Self {
+        Range::new(Bound::Unbounded, Bound::Included(range.end))
+    }
+}
+
+impl<T> From<ops::RangeFull> for Range<T> {
+    fn from(_: ops::RangeFull) -> Self {
+        Range::unbounded()
+    }
+}
+
+/// A PostgreSQL multirange, a set of non-overlapping ranges
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Multirange<T>(pub Vec<Range<T>>);
+
+/// Create the [`Type`] of a user-defined range, such as one created with `CREATE TYPE timerange AS RANGE`
+pub fn range_type(name: &str, oid: u32, subtype: Type, schema: &str) -> Type {
+    Type::new(name.to_owned(), oid, Kind::Range(subtype), schema.to_owned())
+}
+
+/// Create the [`Type`] of a user-defined multirange of ranges of `subtype`, see [`range_type`]
+pub fn multirange_type(name: &str, oid: u32, subtype: Type, schema: &str) -> Type {
+    Type::new(name.to_owned(), oid, Kind::Multirange(subtype), schema.to_owned())
+}
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+/// Write a length-prefixed value, failing if it is `NULL`
+fn write_prefixed(
+    buf: &mut BytesMut,
+    f: impl FnOnce(&mut BytesMut) -> Result<IsNull, BoxError>,
+) -> Result<(), BoxError> {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+
+    if let IsNull::Yes = f(buf)? {
+        return Err("range bounds cannot be NULL".into());
+    }
+
+    let len = i32::try_from(buf.len() - start - 4)?;
+    buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+
+    Ok(())
+}
+
+fn read_prefixed<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], BoxError> {
+    let (len, rest) = buf.split_first_chunk::<4>().ok_or("invalid buffer size")?;
+    let len = usize::try_from(i32::from_be_bytes(*len))?;
+
+    if rest.len() < len {
+        return Err("invalid buffer size".into());
+    }
+
+    let (value, rest) = rest.split_at(len);
+    *buf = rest;
+
+    Ok(value)
+}
+
+impl<T: ToSql> Range<T> {
+    fn write_binary(&self, subtype: &Type, buf: &mut BytesMut) -> Result<(), BoxError> {
+        let Range::Nonempty(lower, upper) = self else {
+            buf.extend_from_slice(&[RANGE_EMPTY]);
+            return Ok(());
+        };
+
+        let mut flags = 0;
+
+        for (bound, inc, inf) in [(lower, RANGE_LB_INC, RANGE_LB_INF), (upper, RANGE_UB_INC, RANGE_UB_INF)] {
+            flags |= match bound {
+                Bound::Included(_) => inc,
+                Bound::Excluded(_) => 0,
+                Bound::Unbounded => inf,
+            };
+        }
+
+        buf.extend_from_slice(&[flags]);
+
+        for bound in [lower, upper] {
+            if let Bound::Included(value) | Bound::Excluded(value) = bound {
+                write_prefixed(buf, |buf| value.to_sql(subtype, buf))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: FromSql<'a>> Range<T> {
+    fn read_binary(subtype: &Type, mut buf: &'a [u8]) -> Result<Self, BoxError> {
+        let (&flags, rest) = buf.split_first().ok_or("invalid buffer size")?;
+        buf = rest;
+
+        if flags & RANGE_EMPTY != 0 {
+            return Ok(Range::Empty);
+        }
+
+        let mut bound = |inc: u8, inf: u8| -> Result<Bound<T>, BoxError> {
+            if flags & inf != 0 {
+                return Ok(Bound::Unbounded);
+            }
+
+            let value = T::from_sql(subtype, read_prefixed(&mut buf)?)?;
+
+            Ok(match flags & inc != 0 {
+                true => Bound::Included(value),
+                false => Bound::Excluded(value),
+            })
+        };
+
+        let lower = bound(RANGE_LB_INC, RANGE_LB_INF)?;
+        let upper = bound(RANGE_UB_INC, RANGE_UB_INF)?;
+
+        Ok(Range::Nonempty(lower, upper))
+    }
+}
+
+fn range_subtype(ty: &Type) -> Option<&Type> {
+    match ty.kind() {
+        Kind::Range(subtype) => Some(subtype),
+        _ => None,
+    }
+}
+
+fn multirange_subtype(ty: &Type) -> Option<&Type> {
+    match ty.kind() {
+        Kind::Multirange(subtype) => Some(subtype),
+        _ => None,
+    }
+}
+
+impl<T: ToSql> ToSql for Range<T> {
+    fn to_sql(&self, ty: &Type, buf: &mut BytesMut) -> Result<IsNull, BoxError> {
+        self.write_binary(range_subtype(ty).ok_or("expected a range type")?, buf)?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        range_subtype(ty).is_some_and(T::accepts)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a, T: FromSql<'a>> FromSql<'a> for Range<T> {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        Range::read_binary(range_subtype(ty).ok_or("expected a range type")?, raw)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        range_subtype(ty).is_some_and(T::accepts)
+    }
+}
+
+impl<T: ToSql> ToSql for Multirange<T> {
+    fn to_sql(&self, ty: &Type, buf: &mut BytesMut) -> Result<IsNull, BoxError> {
+        let subtype = multirange_subtype(ty).ok_or("expected a multirange type")?;
+
+        buf.extend_from_slice(&i32::try_from(self.0.len())?.to_be_bytes());
+
+        for range in &self.0 {
+            write_prefixed(buf, |buf| range.write_binary(subtype, buf).map(|_| IsNull::No))?;
+        }
+
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        multirange_subtype(ty).is_some_and(T::accepts)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a, T: FromSql<'a>> FromSql<'a> for Multirange<T> {
+    fn from_sql(ty: &Type, mut raw: &'a [u8]) -> Result<Self, BoxError> {
+        let subtype = multirange_subtype(ty).ok_or("expected a multirange type")?;
+
+        let (count, rest) = raw.split_first_chunk::<4>().ok_or("invalid buffer size")?;
+        let count = usize::try_from(i32::from_be_bytes(*count))?;
+        raw = rest;
+
+        let mut ranges = Vec::with_capacity(count.min(raw.len()));
+
+        for _ in 0..count {
+            ranges.push(Range::read_binary(subtype, read_prefixed(&mut raw)?)?);
+        }
+
+        Ok(Multirange(ranges))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        multirange_subtype(ty).is_some_and(T::accepts)
+    }
+}
+
+impl<T: Literal> Range<T> {
+    /// Write the range in its text format, such as `[1,10)`
+    fn write_text(&self, buf: &mut String) -> fmt::Result {
+        let Range::Nonempty(lower, upper) = self else {
+            buf.push_str("empty");
+            return Ok(());
+        };
+
+        buf.push(if let Bound::Included(_) = lower { '[' } else { '(' });
+
+        if let Bound::Included(value) | Bound::Excluded(value) = lower {
+            write_field(buf, value, 0)?;
+        }
+
+        buf.push(',');
+
+        if let Bound::Included(value) | Bound::Excluded(value) = upper {
+            write_field(buf, value, 0)?;
+        }
+
+        buf.push(if let Bound::Included(_) = upper { ']' } else { ')' });
+
+        Ok(())
+    }
+}
+
+/// Ranges are written as string constants, such as `'[1,10)'`, whose type is inferred from context
+impl<T: Literal> Literal for Range<T> {
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        let mut buf = String::new();
+        self.write_text(&mut buf)?;
+
+        match depth {
+            0 => escape::write_string(&buf, w),
+            _ => escape::write_array_element(&buf, w),
+        }
+    }
+}
+
+/// Multiranges are written as string constants, such as `'{[1,3), [5,7)}'`
+impl<T: Literal> Literal for Multirange<T> {
+    fn write_literal(&self, w: &mut dyn Write, depth: usize) -> fmt::Result {
+        let mut buf = String::from("{");
+
+        for (i, range) in self.0.iter().enumerate() {
+            if i > 0 {
+                buf.push_str(", ");
+            }
+
+            range.write_text(&mut buf)?;
+        }
+
+        buf.push('}');
+
+        match depth {
+            0 => escape::write_string(&buf, w),
+            _ => escape::write_array_element(&buf, w),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(value: impl Literal) -> String {
+        let mut buf = String::new();
+        value.write_literal(&mut buf, 0).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(lit(Range::from(1..10)), "'[1,10)'");
+        assert_eq!(lit(Range::from(..=10)), "'(,10]'");
+        assert_eq!(lit(Range::<i32>::empty()), "'empty'");
+        assert_eq!(
+            lit(Range::new(Bound::Excluded("a, b"), Bound::Unbounded)),
+            r#"'("a, b",)'"#
+        );
+        assert_eq!(lit(Multirange(vec![(1..3).into(), (5..).into()])), "'{[1,3), [5,)}'");
+        assert_eq!(lit([Range::from(1..2)]), r#"'{"[1,2)"}'"#);
+
+        let mut buf = BytesMut::new();
+        let range = Multirange(vec![Range::from(1i64..3), Range::from(5..), Range::empty()]);
+        range.to_sql(&Type::INT8MULTI_RANGE, &mut buf).unwrap();
+
+        let parsed = Multirange::<i64>::from_sql(&Type::INT8MULTI_RANGE, &buf).unwrap();
+        assert_eq!(parsed, range);
+
+        assert!(<Range<i64> as ToSql>::accepts(&Type::INT8_RANGE));
+        assert!(!<Range<i64> as ToSql>::accepts(&Type::INT4_RANGE));
+
+        use crate::ty::TypeExt;
+        assert_eq!(
+            Type::INT8_RANGE.rust_type().as_deref(),
+            Some("thorn::range::Range<i64>")
+        );
+    }
+
+    /// Set `THORN_TEST_DB` to a connection string to run
+    #[cfg(feature = "extensions")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_range_round_trip() {
+        let db = std::env::var("THORN_TEST_DB").expect("THORN_TEST_DB is not set");
+        let (client, conn) = pgt::connect(&db, pgt::NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        let range = Range::new(Bound::Excluded(1i64), Bound::Included(10));
+        let multi = Multirange(vec![Range::from(..0i32), Range::from(5..7)]);
+
+        let sql = format!(
+            "SELECT $1::int8range, $2::int4multirange, {}::int8range, {}::int4multirange",
+            lit(range),
+            lit(&multi)
+        );
+        let row = client.query_one(&sql, &[&range, &multi]).await.unwrap();
+
+        // canonicalized by PostgreSQL
+        let canonical = Range::from(2i64..11);
+
+        assert_eq!(row.get::<_, Range<i64>>(0), canonical);
+        assert_eq!(row.get::<_, Multirange<i32>>(1), multi);
+        assert_eq!(row.get::<_, Range<i64>>(2), canonical);
+        assert_eq!(row.get::<_, Multirange<i32>>(3), multi);
+    }
+}
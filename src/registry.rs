@@ -0,0 +1,228 @@
+//! Global registry of static `sql!` queries and `tables!` definitions.
+//!
+//! Every static query that does not reference local variables is collected at link-time,
+//! so a service can render and prepare all of them at startup to catch SQL or schema
+//! errors at deploy time, rather than on the first request that happens to run them.
+//!
+//! Tables are collected so that names reported by the database can be resolved back to
+//! their Rust definitions.
+
+#[cfg(feature = "registry")]
+pub use self::imp::*;
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "registry")]
+macro_rules! __register_query {
+    ($query:ident, $build:ident) => {
+        #[$crate::linkme::distributed_slice($crate::registry::STATIC_QUERIES)]
+        #[linkme(crate = $crate::linkme)]
+        static __THORN_REGISTERED: $crate::registry::RegisteredQuery = $crate::registry::RegisteredQuery {
+            location: $crate::macros::SourceLocation {
+                file: file!(),
+                line: line!(),
+                column: column!(),
+            },
+            module_path: module_path!(),
+            render: || match $query.get_or_init($build) {
+                Ok(q) => Ok((q.q.as_str(), q.params.as_slice())),
+                Err(e) => Err(e.clone()),
+            },
+        };
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "registry")]
+macro_rules! __register_table {
+    ($table:ident { $($column:ident),* }) => {
+        const _: () = {
+            #[$crate::linkme::distributed_slice($crate::registry::TABLES)]
+            #[linkme(crate = $crate::linkme)]
+            static __THORN_TABLE: $crate::registry::RegisteredTable = $crate::registry::RegisteredTable {
+                schema: <$table as $crate::Table>::SCHEMA,
+                name: <$table as $crate::Table>::NAME,
+                typename: stringify!($table),
+                module_path: module_path!(),
+                columns: &[$(&$table::$column as &(dyn $crate::table::Column + Sync)),*],
+            };
+        };
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "registry"))]
+macro_rules! __register_query {
+    ($query:ident, $build:ident) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(feature = "registry"))]
+macro_rules! __register_table {
+    ($table:ident { $($column:ident),* }) => {};
+}
+
+#[cfg(feature = "registry")]
+mod imp {
+    use std::fmt;
+
+    use crate::macros::{SourceLocation, SqlFormatError};
+    use crate::name::{Name, Schema};
+    use crate::table::Column;
+
+    type RenderedQuery = (&'static str, &'static [pg::Type]);
+
+    #[doc(hidden)]
+    #[linkme::distributed_slice]
+    pub static STATIC_QUERIES: [RegisteredQuery];
+
+    /// A static `sql!` query and the source location it was defined at
+    pub struct RegisteredQuery {
+        pub location: SourceLocation,
+        pub module_path: &'static str,
+
+        #[doc(hidden)]
+        pub render: fn() -> Result<RenderedQuery, SqlFormatError>,
+    }
+
+    impl RegisteredQuery {
+        /// Render the query text and parameter types, building the cached query if needed.
+        #[inline]
+        pub fn sql(&self) -> Result<RenderedQuery, SqlFormatError> {
+            (self.render)()
+        }
+    }
+
+    impl fmt::Debug for RegisteredQuery {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RegisteredQuery")
+                .field("location", &self.location)
+                .field("module_path", &self.module_path)
+                .finish()
+        }
+    }
+
+    impl fmt::Display for RegisteredQuery {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.location.fmt(f)
+        }
+    }
+
+    /// All static queries linked into the current binary
+    #[inline]
+    pub fn queries() -> &'static [RegisteredQuery] {
+        &STATIC_QUERIES
+    }
+
+    #[doc(hidden)]
+    #[linkme::distributed_slice]
+    pub static TABLES: [RegisteredTable];
+
+    /// A table defined with `tables!`
+    pub struct RegisteredTable {
+        pub schema: Schema,
+        pub name: Name,
+        pub typename: &'static str,
+        pub module_path: &'static str,
+        pub columns: &'static [&'static (dyn Column + Sync)],
+    }
+
+    impl RegisteredTable {
+        /// Find a column by its SQL name
+        pub fn column(&self, name: &str) -> Option<&'static (dyn Column + Sync)> {
+            self.columns.iter().copied().find(|c| c.name() == name)
+        }
+    }
+
+    impl fmt::Debug for RegisteredTable {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RegisteredTable")
+                .field("schema", &self.schema)
+                .field("name", &self.name)
+                .field("typename", &self.typename)
+                .field("module_path", &self.module_path)
+                .finish_non_exhaustive()
+        }
+    }
+
+    /// All tables linked into the current binary
+    #[inline]
+    pub fn tables() -> &'static [RegisteredTable] {
+        &TABLES
+    }
+
+    /// Find a table by its SQL schema and name. Tables defined without a schema
+    /// match any schema, but tables with an exactly matching schema are preferred.
+    pub fn find_table(schema: Option<&str>, name: &str) -> Option<&'static RegisteredTable> {
+        let mut fallback = None;
+
+        for table in tables() {
+            if table.name.name() != name {
+                continue;
+            }
+
+            match (table.schema, schema) {
+                (Schema::Named(a), Some(b)) if a == b => return Some(table),
+                (Schema::None, _) => fallback = fallback.or(Some(table)),
+                _ => {}
+            }
+        }
+
+        fallback
+    }
+
+    #[cfg(feature = "extensions")]
+    #[derive(Debug, thiserror::Error)]
+    #[error("Query at {query} failed to prepare: {error}")]
+    pub struct WarmupError {
+        pub query: &'static RegisteredQuery,
+        pub error: crate::extensions::Error,
+    }
+
+    /// Render and prepare every registered query, returning all failures.
+    #[cfg(feature = "extensions")]
+    pub async fn prepare_all(client: &pgt::Client) -> Vec<WarmupError> {
+        let mut errors = Vec::new();
+
+        for query in queries() {
+            let res = match query.sql() {
+                Ok((q, tys)) => client.prepare_typed(q, tys).await.map(|_| ()).map_err(From::from),
+                Err(e) => Err(e.into()),
+            };
+
+            if let Err(error) = res {
+                errors.push(WarmupError { query, error });
+            }
+        }
+
+        errors
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::pg::Type;
+
+        crate::tables! {
+            pub struct RegistryTable {
+                Id: Type::INT8,
+            }
+        }
+
+        #[test]
+        fn test_registered() {
+            let _ = sql! {
+                SELECT RegistryTable.Id FROM RegistryTable WHERE RegistryTable.Id = #{&1i64 as RegistryTable::Id}
+            };
+
+            let query = super::queries().iter().find(|q| q.module_path == module_path!()).unwrap();
+            let (q, tys) = query.sql().unwrap();
+
+            assert_eq!(query.location.file, file!());
+            assert!(q.ends_with("$1"));
+            assert_eq!(tys, &[Type::INT8]);
+        }
+    }
+}
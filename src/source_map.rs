@@ -0,0 +1,252 @@
+//! Mapping from byte offsets in rendered SQL back to the `sql!` tokens that produced them.
+
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+use crate::macros::SourceLocation;
+
+/// A token in the `sql!` invocation, located in the same file as the invocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceToken {
+    pub line: u32,
+    pub column: u32,
+    pub text: &'static str,
+}
+
+/// Static table generated for each `sql!` invocation.
+#[derive(Debug)]
+pub struct SourceMapTable {
+    pub tokens: &'static [SourceToken],
+
+    /// For each chunk of text written to the query, the `(offset, token)` pairs
+    /// of tokens starting within that chunk.
+    pub chunks: &'static [&'static [(u32, u32)]],
+}
+
+static EMPTY_TABLE: SourceMapTable = SourceMapTable {
+    tokens: &[],
+    chunks: &[],
+};
+
+/// Records where each chunk of a query was written at runtime.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    table: &'static SourceMapTable,
+    /// `(offset, chunk)` pairs, in order of offset
+    marks: Vec<(u32, u32)>,
+    /// Chunk of the next text written to the query
+    pending: Option<u32>,
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        SourceMap::new(&EMPTY_TABLE)
+    }
+}
+
+impl SourceMap {
+    #[inline]
+    pub const fn new(table: &'static SourceMapTable) -> Self {
+        SourceMap {
+            table,
+            marks: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// Map the next text written to the query to `chunk`
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __mark(&mut self, chunk: u32) {
+        self.pending = Some(chunk);
+    }
+
+    /// Text was written to the query at `offset`
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __written(&mut self, offset: usize) {
+        if let Some(chunk) = self.pending.take() {
+            self.marks.push((offset as u32, chunk));
+        }
+    }
+
+    /// Find the source token that produced the text at byte `offset` in `sql`,
+    /// and the byte range of the text it produced.
+    pub fn token_at(&self, sql: &str, offset: usize) -> Option<(&'static SourceToken, Range<usize>)> {
+        let mark_idx = self.marks.partition_point(|&(o, _)| o as usize <= offset).checked_sub(1)?;
+        let (chunk_start, chunk) = self.marks[mark_idx];
+
+        let entries = self.table.chunks.get(chunk as usize)?;
+        let local = (offset - chunk_start as usize) as u32;
+        let entry_idx = entries.partition_point(|&(o, _)| o <= local).checked_sub(1)?;
+        let (token_offset, token) = entries[entry_idx];
+
+        let start = (chunk_start + token_offset) as usize;
+        let end = match entries.get(entry_idx + 1) {
+            Some(&(next, _)) => (chunk_start + next) as usize,
+            None => match self.marks.get(mark_idx + 1) {
+                Some(&(next, _)) => next as usize,
+                None => sql.len(),
+            },
+        };
+
+        let end = start + sql.get(start..end)?.trim_end().len();
+
+        Some((self.table.tokens.get(token as usize)?, start..end.max(start + 1)))
+    }
+}
+
+/// Location of an error within a rendered query, displayed with a caret underline
+#[derive(Debug, Clone)]
+pub struct SqlSpan {
+    pub sql: String,
+    /// Byte range of the offending text in `sql`
+    pub range: Range<usize>,
+    /// The `sql!` token that produced the offending text, if known
+    pub token: Option<SourceToken>,
+    /// Where the query was defined
+    pub location: SourceLocation,
+}
+
+impl SqlSpan {
+    /// Maps a 1-indexed character position as reported by PostgreSQL
+    pub fn from_position(sql: &str, map: &SourceMap, location: SourceLocation, position: usize) -> Self {
+        let offset = match sql.char_indices().nth(position.saturating_sub(1)) {
+            Some((offset, _)) => offset,
+            None => sql.len(),
+        };
+
+        let (token, mut range) = match map.token_at(sql, offset) {
+            Some((token, range)) => (Some(*token), range),
+            None => (None, offset..offset + 1),
+        };
+
+        // the error may point into the middle of a token
+        if !range.contains(&offset) {
+            range = offset..offset + 1;
+        }
+
+        range.start = offset;
+
+        SqlSpan {
+            sql: sql.to_owned(),
+            range,
+            token,
+            location,
+        }
+    }
+
+    /// The Rust source location of the offending token, or of the query if unknown
+    pub fn source_location(&self) -> SourceLocation {
+        match self.token {
+            Some(token) => SourceLocation {
+                file: self.location.file,
+                line: token.line,
+                column: token.column,
+            },
+            None => self.location,
+        }
+    }
+}
+
+/// Maximum number of characters shown on either side of the error
+const CONTEXT: usize = 60;
+
+impl fmt::Display for SqlSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "  --> {}", self.source_location())?;
+
+        if let Some(token) = self.token {
+            write!(f, " `{}`", token.text)?;
+        }
+
+        let start = self.range.start.min(self.sql.len());
+
+        // window of the line containing the error, trimmed to a reasonable width
+        let line_start = self.sql[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.sql[start..].find('\n').map_or(self.sql.len(), |i| start + i);
+
+        // tokens such as multi-line strings are only highlighted up to the end of their first line
+        let end = self.range.end.clamp(start, line_end);
+
+        let window_start = self.sql[line_start..start]
+            .char_indices()
+            .rev()
+            .nth(CONTEXT - 1)
+            .map_or(line_start, |(i, _)| line_start + i);
+
+        let window_end = self.sql[end..line_end].char_indices().nth(CONTEXT).map_or(line_end, |(i, _)| end + i);
+
+        let prefix = if window_start > line_start { "..." } else { "" };
+        let suffix = if window_end < line_end { "..." } else { "" };
+
+        f.write_str("\n   |\n   | ")?;
+        write!(f, "{prefix}{}{suffix}", &self.sql[window_start..window_end])?;
+        f.write_str("\n   | ")?;
+
+        let pad = prefix.len() + self.sql[window_start..start].chars().count();
+        let carets = self.sql[start..end.min(window_end)].chars().count().max(1);
+
+        for _ in 0..pad {
+            f.write_char(' ')?;
+        }
+        for _ in 0..carets {
+            f.write_char('^')?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pg::Type;
+
+    crate::tables! {
+        pub struct MapTable {
+            Id: Type::INT8,
+        }
+    }
+
+    #[test]
+    fn test_token_at() {
+        let query = sql! {
+            SELECT MapTable.Id FROM MapTable WHERE MapTable.Id = #{&1i64 as MapTable::Id}
+        }
+        .unwrap();
+
+        let sql = query.sql();
+        let map = query.source_map();
+
+        let (token, range) = map.token_at(sql, sql.find("FROM").unwrap()).unwrap();
+        assert_eq!(token.text, "FROM");
+        assert_eq!(&sql[range], "FROM");
+        assert_eq!(token.line, line!() - 10);
+
+        let (token, range) = map.token_at(sql, sql.find("\"map_table\" ").unwrap()).unwrap();
+        assert_eq!(token.text, "MapTable");
+        assert_eq!(&sql[range], "\"map_table\"");
+
+        let (token, _) = map.token_at(sql, sql.find('$').unwrap()).unwrap();
+        assert!(token.text.starts_with('#'));
+    }
+
+    #[test]
+    fn test_multiline_span() {
+        let sql = "SELECT 1 + $$first\nsecond$$, 2";
+        let start = sql.find("$$").unwrap();
+
+        let span = SqlSpan {
+            sql: sql.to_owned(),
+            range: start..sql.rfind("$$").unwrap() + 2,
+            token: None,
+            location: SourceLocation::default(),
+        };
+
+        let out = span.to_string();
+        let (_, snippet) = out.split_once('\n').unwrap();
+
+        assert_eq!(snippet, "   |\n   | SELECT 1 + $$first\n   |            ^^^^^^^");
+    }
+}
@@ -33,6 +33,132 @@ pub trait Column: 'static {
     fn name(&self) -> &'static str;
     fn ty(&self) -> ColumnType;
     fn comment(&self) -> &'static str;
+
+    /// Whether values of this column should be redacted from debug output,
+    /// set with `#[sensitive]` in [`tables!`]
+    #[inline]
+    fn sensitive(&self) -> bool {
+        false
+    }
+}
+
+/// Rust types that may be passed as a parameter for a column declared with the Rust type `T`
+/// in [`tables!`], such as `&str` for a `String` column or `i64` for an `Option<i64>` column.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be used as a parameter for a column of type `{T}`")]
+pub trait ColumnValue<T: ?Sized> {}
+
+impl<T: ?Sized> ColumnValue<T> for T {}
+impl<T: ?Sized> ColumnValue<T> for &T {}
+
+impl ColumnValue<String> for str {}
+impl ColumnValue<String> for &str {}
+impl ColumnValue<String> for Box<str> {}
+impl ColumnValue<String> for std::borrow::Cow<'_, str> {}
+
+impl<T> ColumnValue<Vec<T>> for [T] {}
+impl<T> ColumnValue<Vec<T>> for &[T] {}
+impl<T> ColumnValue<Vec<T>> for Box<[T]> {}
+impl<T, const N: usize> ColumnValue<Vec<T>> for [T; N] {}
+
+impl<T> ColumnValue<Option<T>> for T {}
+impl<T> ColumnValue<Option<T>> for &T {}
+impl<T> ColumnValue<Option<T>> for Option<&T> {}
+impl ColumnValue<Option<String>> for str {}
+impl ColumnValue<Option<String>> for &str {}
+impl ColumnValue<Option<String>> for Option<&str> {}
+
+/// Containers whose elements may each be passed as a parameter for the elements of a column declared with
+/// the Rust type `T`, such as `Vec<&str>` for a `Vec<String>` column or `Option<&str>` for an `Option<String>` column.
+///
+/// These can't be [`ColumnValue`] impls, as they would overlap with `ColumnValue<T> for T`,
+/// so `sql!` only checks them when the parameter is not a [`ColumnValue`] itself.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a parameter for a column of type `{T}`",
+    label = "the trait `ColumnValue<{T}>` is not implemented for `{Self}`"
+)]
+pub trait ColumnElements<T: ?Sized> {}
+
+impl<T, U: ColumnValue<T>> ColumnElements<Vec<T>> for Vec<U> {}
+impl<T, U: ColumnValue<T>> ColumnElements<Vec<T>> for [U] {}
+impl<T, U: ColumnValue<T>> ColumnElements<Vec<T>> for &[U] {}
+impl<T, U: ColumnValue<T>> ColumnElements<Vec<T>> for Box<[U]> {}
+impl<T, U: ColumnValue<T>, const N: usize> ColumnElements<Vec<T>> for [U; N] {}
+impl<T, U: ColumnValue<T>> ColumnElements<Option<T>> for Option<U> {}
+
+/// The Rust type declared in [`tables!`] for the column of `Self` whose name hashes to `NAME`,
+/// see [`__column_hash`]
+#[doc(hidden)]
+pub trait ColumnRust<const NAME: u64> {
+    type Rust: ?Sized;
+}
+
+/// FNV-1a hash of a column name, as a const parameter of [`ColumnRust`]
+#[doc(hidden)]
+pub const fn __column_hash(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+
+    hash
+}
+
+/// Used by `sql!` to check parameters of type `V` typed by `T`, such as `#{&id as Users::Id}`, against the
+/// declared Rust type of the column, through autoref specialization so that any other type is left unchecked.
+///
+/// Parameters are first checked as a [`ColumnValue`], then as [`ColumnElements`].
+#[doc(hidden)]
+pub struct ParamCheck<T, const NAME: u64, V: ?Sized>(std::marker::PhantomData<T>, std::marker::PhantomData<V>);
+
+impl<T, const NAME: u64, V: ?Sized> ParamCheck<T, NAME, V> {
+    #[inline(always)]
+    pub fn new(_: &T, _: &V) -> Self {
+        ParamCheck(std::marker::PhantomData, std::marker::PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait CheckColumnParam {
+    #[inline(always)]
+    fn __check<V: ?Sized>(&self, _: &V) {}
+}
+
+impl<T: ColumnRust<NAME>, const NAME: u64, V> CheckColumnParam for &&ParamCheck<T, NAME, V> where
+    V: ?Sized + ColumnValue<T::Rust>
+{
+}
+
+#[doc(hidden)]
+pub trait CheckColumnElements {
+    type Rust: ?Sized;
+
+    #[inline(always)]
+    fn __check<V: ?Sized + ColumnElements<Self::Rust>>(&self, _: &V) {}
+}
+
+impl<T: ColumnRust<NAME>, const NAME: u64, V: ?Sized> CheckColumnElements for &ParamCheck<T, NAME, V> {
+    type Rust = T::Rust;
+}
+
+#[doc(hidden)]
+pub trait SkipColumnParam {
+    #[inline(always)]
+    fn __check<V: ?Sized>(&self, _: &V) {}
+}
+
+impl<T, const NAME: u64, V: ?Sized> SkipColumnParam for ParamCheck<T, NAME, V> {}
+
+/// Error parsing a column from a string that is not a column of the table
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown column \"{name}\" of table {table}")]
+pub struct UnknownColumn {
+    pub table: &'static str,
+    pub name: String,
 }
 
 pub trait Table: Clone + Copy + Column + Sized + 'static {
@@ -45,6 +171,9 @@ pub trait Table: Clone + Copy + Column + Sized + 'static {
 pub trait TableExt: Table {
     const TYPENAME: &'static str;
     const TYPENAME_SNAKE: &'static str;
+
+    /// All columns of the table, in declaration order
+    const COLUMNS: &'static [Self];
 }
 
 #[macro_export]
@@ -56,11 +185,26 @@ macro_rules! tables {
         concat!($($crate::tables!(@DOC # $meta)),*)
     };
 
+    (@SENSITIVE #[sensitive]) => { true };
+    (@SENSITIVE #[$($meta:tt)*]) => { false };
+
+    (@SENSITIVE_START $(#[$($meta:tt)*])*) => {
+        false $(|| $crate::tables!(@SENSITIVE #[$($meta)*]))*
+    };
+
+    (@RUST $table:ident $field_name:ident) => {};
+
+    (@RUST $table:ident $field_name:ident $rust:ty) => {
+        impl $crate::table::ColumnRust<{ $crate::table::__column_hash(stringify!($field_name)) }> for $table {
+            type Rust = $rust;
+        }
+    };
+
     ($($(#[$($meta:tt)*])* $struct_vis:vis struct $table:ident $(as $rename:tt)? $(in $schema:ident)? {$(
-        $(#[$($field_meta:tt)*])* $field_name:ident: $ty:expr
+        $(#[$($field_meta:tt)*])* $field_name:ident: $ty:expr $(=> $rust:ty)?
     ),*$(,)?})*) => {$crate::paste::paste! {$(
         $(#[$($meta)*])*
-        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, $crate::thorn_macros::__Columns)]
         $struct_vis enum $table {
             $($(#[$($field_meta)*])* $field_name,)*
         }
@@ -77,11 +221,13 @@ macro_rules! tables {
         impl $crate::table::TableExt for $table {
             const TYPENAME: &'static str = stringify!($table);
             const TYPENAME_SNAKE: &'static str = stringify!([<$table:snake>]);
+            const COLUMNS: &'static [Self] = &[$($table::$field_name,)*];
         }
 
-        // impl $crate::table::RealTable for $table {
-        //     const COLUMNS: &'static [Self] = &[$($table::$field_name,)*];
-        // }
+        $crate::__register_table!($table { $($field_name),* });
+
+        // used by `sql!` to check the Rust types of parameters typed by a column
+        $($crate::tables!(@RUST $table $field_name $($rust)?);)*
 
         impl $crate::table::Column for $table {
             #[inline]
@@ -103,6 +249,40 @@ macro_rules! tables {
                     $($table::$field_name => $crate::tables!(@DOC_START $(#[$($field_meta)*])*)),*
                 }
             }
+
+            #[inline]
+            fn sensitive(&self) -> bool {
+                match *self {
+                    $($table::$field_name => $crate::tables!(@SENSITIVE_START $(#[$($field_meta)*])*)),*
+                }
+            }
+        }
+
+        /// Parses a column by its SQL name or Rust name, such as `user_name` or `UserName`
+        impl std::str::FromStr for $table {
+            type Err = $crate::table::UnknownColumn;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(
+                    if s == stringify!([<$field_name:snake>]) || s == stringify!($field_name) {
+                        return Ok($table::$field_name);
+                    }
+                )*
+
+                Err($crate::table::UnknownColumn {
+                    table: stringify!($table),
+                    name: s.to_owned(),
+                })
+            }
+        }
+
+        impl TryFrom<&str> for $table {
+            type Error = $crate::table::UnknownColumn;
+
+            #[inline]
+            fn try_from(s: &str) -> Result<Self, Self::Error> {
+                s.parse()
+            }
         }
 
         impl From<$table> for $crate::table::ColumnType {
@@ -133,9 +313,10 @@ tables! {
     #[derive(Debug)]
     pub struct TestTable as "tt" in TestSchema {
         /// Some identifier
-        Id: Type::INT8,
+        Id: Type::INT8 => i64,
         /// Username
-        UserName: Type::TEXT,
+        #[sensitive]
+        UserName: Type::TEXT => String,
     }
 
     pub(crate) struct SchemaColumns as "columns" in InformationSchema {
@@ -157,6 +338,52 @@ tables! {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_column() {
+        assert!(matches!("user_name".parse(), Ok(TestTable::UserName)));
+        assert!(matches!(TestTable::try_from("UserName"), Ok(TestTable::UserName)));
+        assert_eq!(
+            "password".parse::<TestTable>().unwrap_err().to_string(),
+            r#"Unknown column "password" of table TestTable"#
+        );
+
+        let sort: TestTable = "user_name".parse().unwrap();
+        let query = sql! { SELECT TestTable.Id FROM TestTable ORDER BY {sort!} DESC }.unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"SELECT "tt"."id" FROM "test_schema"."tt" ORDER BY "tt"."user_name" DESC"#
+        );
+
+        // qualified with the alias in scope
+        let query = sql! { SELECT Renamed.Id FROM TestTable AS Renamed ORDER BY {sort!} DESC }.unwrap();
+
+        assert_eq!(
+            query.sql(),
+            r#"SELECT "renamed"."id" FROM "test_schema"."tt" AS "renamed" ORDER BY "renamed"."user_name" DESC"#
+        );
+
+        let query = sql! {
+            SELECT TestTable.Id FROM TestTable
+            WHERE EXISTS (SELECT 1 FROM TestTable AS Other WHERE {sort!} = TestTable.UserName)
+            ORDER BY {sort!}
+        }
+        .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            concat!(
+                r#"SELECT "tt"."id" FROM "test_schema"."tt" WHERE EXISTS (SELECT 1 FROM "test_schema"."tt" AS "other" "#,
+                r#"WHERE "other"."user_name" = "tt"."user_name") ORDER BY "tt"."user_name""#
+            )
+        );
+    }
+}
+
 // pub trait RealTable: Table {
 //     const COLUMNS: &'static [Self];
 
@@ -1,9 +1,16 @@
+use std::borrow::Cow;
+
 use pg::{Kind, Type};
 
 pub trait TypeExt {
     fn is_boolean(&self) -> bool;
     fn is_array(&self) -> bool;
     fn is_composite(&self) -> bool;
+
+    /// The Rust type for values of this type that can be written as a [`Literal`](crate::literal::Literal),
+    /// including types from the optional `chrono`, `time`, `uuid`, `serde_json`, `rust_decimal`,
+    /// `ipnetwork` and `cidr` features when enabled.
+    fn rust_type(&self) -> Option<Cow<'static, str>>;
 }
 
 impl TypeExt for Type {
@@ -18,4 +25,66 @@ impl TypeExt for Type {
     fn is_composite(&self) -> bool {
         matches!(self.kind(), Kind::Composite(_))
     }
+
+    fn rust_type(&self) -> Option<Cow<'static, str>> {
+        match *self.kind() {
+            Kind::Array(ref elem) => return elem.rust_type().map(|elem| format!("Vec<{elem}>").into()),
+            Kind::Range(ref elem) => {
+                return elem.rust_type().map(|elem| format!("thorn::range::Range<{elem}>").into())
+            }
+            Kind::Multirange(ref elem) => {
+                return elem.rust_type().map(|elem| format!("thorn::range::Multirange<{elem}>").into())
+            }
+            _ => {}
+        }
+
+        Some(Cow::Borrowed(match *self {
+            Type::BOOL => "bool",
+            Type::INT2 => "i16",
+            Type::INT4 => "i32",
+            Type::INT8 => "i64",
+            Type::OID => "u32",
+            Type::FLOAT4 => "f32",
+            Type::FLOAT8 => "f64",
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => "String",
+            Type::BYTEA => "Vec<u8>",
+
+            #[cfg(feature = "chrono")]
+            Type::DATE => "chrono::NaiveDate",
+            #[cfg(feature = "chrono")]
+            Type::TIME => "chrono::NaiveTime",
+            #[cfg(feature = "chrono")]
+            Type::TIMESTAMP => "chrono::NaiveDateTime",
+            #[cfg(feature = "chrono")]
+            Type::TIMESTAMPTZ => "chrono::DateTime<chrono::Utc>",
+
+            #[cfg(all(feature = "time", not(feature = "chrono")))]
+            Type::DATE => "time::Date",
+            #[cfg(all(feature = "time", not(feature = "chrono")))]
+            Type::TIME => "time::Time",
+            #[cfg(all(feature = "time", not(feature = "chrono")))]
+            Type::TIMESTAMP => "time::PrimitiveDateTime",
+            #[cfg(all(feature = "time", not(feature = "chrono")))]
+            Type::TIMESTAMPTZ => "time::OffsetDateTime",
+
+            #[cfg(feature = "uuid")]
+            Type::UUID => "uuid::Uuid",
+
+            #[cfg(feature = "serde_json")]
+            Type::JSON | Type::JSONB => "serde_json::Value",
+
+            #[cfg(feature = "rust_decimal")]
+            Type::NUMERIC => "rust_decimal::Decimal",
+
+            #[cfg(feature = "cidr")]
+            Type::INET => "cidr::IpInet",
+            #[cfg(feature = "cidr")]
+            Type::CIDR => "cidr::IpCidr",
+
+            #[cfg(all(feature = "ipnetwork", not(feature = "cidr")))]
+            Type::INET | Type::CIDR => "ipnetwork::IpNetwork",
+
+            _ => return None,
+        }))
+    }
 }
@@ -0,0 +1,17 @@
+//! `thorn_macros` keeps copies of some of thorn's sources, so that it can be packaged on its own.
+
+#[test]
+fn escape_copies_match() {
+    assert!(
+        include_str!("../src/escape.rs") == include_str!("../thorn_macros/src/escape.rs"),
+        "src/escape.rs and thorn_macros/src/escape.rs differ"
+    );
+}
+
+#[test]
+fn spacing_copies_match() {
+    assert!(
+        include_str!("../src/spacing.rs") == include_str!("../thorn_macros/src/spacing.rs"),
+        "src/spacing.rs and thorn_macros/src/spacing.rs differ"
+    );
+}
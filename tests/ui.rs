@@ -0,0 +1,13 @@
+//! Compile-time checks of `sql!`, with the expected errors in `tests/ui/fail/*.stderr`.
+//!
+//! Run with `TRYBUILD=overwrite` to update the expected errors.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+
+    #[cfg(feature = "extensions")]
+    t.compile_fail("tests/ui/fail/extensions/*.rs");
+}
@@ -0,0 +1,15 @@
+use thorn::extensions::{transaction_with_retry, Error, RetryPolicy};
+use thorn::pgt::Client;
+
+// a nested transaction is only a savepoint, which can't be retried on its own
+async fn nested(client: &mut Client) -> Result<(), Error> {
+    let mut tx = client.transaction().await?;
+
+    transaction_with_retry(&mut tx, RetryPolicy::default(), async |_| Ok(())).await?;
+
+    Ok(tx.commit().await?)
+}
+
+fn main() {
+    let _ = nested;
+}
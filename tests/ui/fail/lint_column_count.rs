@@ -0,0 +1,12 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! { INSERT INTO Users (Id, Name) VALUES (1, "a"), (2) };
+}
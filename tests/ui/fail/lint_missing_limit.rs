@@ -0,0 +1,16 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! {
+        #![single]
+
+        SELECT Users.Name FROM Users WHERE Users.Id = 1
+    };
+}
@@ -0,0 +1,14 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! { DELETE FROM Users };
+
+    let _ = thorn::sql! { UPDATE Users SET (Name) = ("x") };
+}
@@ -0,0 +1,7 @@
+fn main() {
+    let _ = thorn::sql! {
+        #![allow(missing_from)]
+
+        SELECT 1
+    };
+}
@@ -0,0 +1,25 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8 => i64,
+        Name: Type::TEXT => String,
+        Bio: Type::TEXT => Option<String>,
+        Labels: Type::TEXT_ARRAY => Vec<String>,
+    }
+}
+
+fn main() {
+    let id = "1";
+    let name = 1i32;
+    let bio = Some(1i64);
+    let labels = vec![1i32];
+
+    let _ = thorn::sql! {
+        SELECT Users.Id FROM Users
+        WHERE Users.Id = #{&id as Users::Id}
+            AND Users.Name = #{&name as Users::Name}
+            AND Users.Bio = #{&bio as Users::Bio}
+            AND Users.Labels = #{&labels as Users::Labels}
+    };
+}
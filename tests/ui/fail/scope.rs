@@ -0,0 +1,38 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+
+    pub struct Posts {
+        Id: Type::INT8,
+        Author: Type::INT8,
+    }
+
+    pub struct Recent {
+        Id: Type::INT8,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! { SELECT Users.Name FROM Posts };
+
+    // the original name is replaced by the alias
+    let _ = thorn::sql! { SELECT Users.Name FROM Users AS Author };
+
+    // `WITH` bodies cannot see the tables of their own query
+    let _ = thorn::sql! {
+        WITH Recent (Id) AS (SELECT Posts.Id FROM Posts WHERE Posts.Author = Users.Id)
+        SELECT Users.Name FROM Users INNER JOIN Recent ON Recent.Id = Users.Id
+    };
+
+    // nor can subqueries in FROM, unless LATERAL
+    let _ = thorn::sql! {
+        SELECT Users.Name FROM Users, (SELECT Posts.Id FROM Posts WHERE Posts.Author = Users.Id)
+    };
+
+    // each statement has its own scope
+    let _ = thorn::sql! { SELECT Users.Name FROM Users; SELECT Users.Id FROM Posts };
+}
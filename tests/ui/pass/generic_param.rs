@@ -0,0 +1,57 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8 => i64,
+    }
+}
+
+trait HasIdType {
+    const ID_TY: Type;
+}
+
+struct Small;
+
+impl HasIdType for Small {
+    const ID_TY: Type = Type::INT4;
+}
+
+struct Large;
+
+impl HasIdType for Large {
+    const ID_TY: Type = Type::INT8;
+}
+
+fn by_generic<T: HasIdType>(id: i64) {
+    let query = thorn::sql! {
+        SELECT Users.Id FROM Users WHERE Users.Id = #{&id as T::ID_TY}
+    }
+    .unwrap();
+
+    assert_eq!(query.param_types()[0], T::ID_TY);
+}
+
+fn by_qualified<T: HasIdType>(id: i64) {
+    let _ = thorn::sql! {
+        SELECT Users.Id FROM Users WHERE Users.Id = #{&id as <T as HasIdType>::ID_TY}
+    };
+}
+
+impl Large {
+    fn by_self(id: i64) {
+        let query = thorn::sql! {
+            SELECT Users.Id FROM Users WHERE Users.Id = #{&id as Self::ID_TY}
+        }
+        .unwrap();
+
+        assert_eq!(query.param_types()[0], Type::INT8);
+    }
+}
+
+fn main() {
+    // the generic query must not be cached for the first instantiation only
+    by_generic::<Small>(1);
+    by_generic::<Large>(1);
+    by_qualified::<Small>(1);
+    Large::by_self(1);
+}
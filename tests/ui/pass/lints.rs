@@ -0,0 +1,23 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! {
+        #![single]
+
+        SELECT Users.Name FROM Users WHERE Users.Id = 1 LIMIT 1
+    };
+
+    let _ = thorn::sql! { DELETE FROM Users WHERE Users.Id = 1 };
+    let _ = thorn::sql! { INSERT INTO Users (Id, Name) VALUES (1, "a"), (2, "b") };
+
+    // the number of values written by a loop is unknown
+    let names = ["a", "b"];
+    let _ = thorn::sql! { INSERT INTO Users (Id, Name) VALUES (1, for name in &names { {name} }) };
+}
@@ -0,0 +1,62 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8 => i64,
+        Name: Type::TEXT => String,
+        Bio: Type::TEXT => Option<String>,
+        Tags: Type::TEXT_ARRAY,
+        Labels: Type::TEXT_ARRAY => Vec<String>,
+    }
+}
+
+mod types {
+    use thorn::pg::Type;
+
+    pub struct Citext;
+
+    impl From<Citext> for Type {
+        fn from(_: Citext) -> Type {
+            Type::TEXT
+        }
+    }
+
+    pub enum Custom {
+        Label,
+    }
+
+    impl From<Custom> for Type {
+        fn from(_: Custom) -> Type {
+            Type::TEXT
+        }
+    }
+}
+
+fn main() {
+    let id = 1i64;
+    let name = "a";
+    let bio = String::from("b");
+    let tags = vec!["c"];
+    let bio_ref = &bio;
+    let nick: Option<Box<str>> = None;
+    let tag_slice = &tags[..];
+    let tag_box: Box<[&str]> = tags.clone().into_boxed_slice();
+    let labels = vec![bio.clone()];
+
+    let _ = thorn::sql! {
+        SELECT Users.Id FROM Users
+        WHERE Users.Id = #{&id as Users::Id}
+            AND Users.Name = #{&name as Users::Name}
+            AND Users.Bio = #{&bio as Users::Bio}
+            AND Users.Tags = #{&tags as Users::Tags}
+            AND Users.Name = #{&name as types::Citext}
+            AND Users.Name = #{&name as types::Custom::Label}
+            AND Users.Id = #{&id as Type::INT8}
+            AND Users.Bio = #{&bio_ref as Users::Bio}
+            AND Users.Bio = #{&nick as Users::Bio}
+            AND Users.Labels = #{&tags as Users::Labels}
+            AND Users.Labels = #{&tag_slice as Users::Labels}
+            AND Users.Labels = #{&tag_box as Users::Labels}
+            AND Users.Labels = #{&labels as Users::Labels}
+    };
+}
@@ -0,0 +1,47 @@
+use thorn::pg::Type;
+
+thorn::tables! {
+    pub struct Users {
+        Id: Type::INT8,
+        Name: Type::TEXT,
+    }
+
+    pub struct Posts {
+        Id: Type::INT8,
+        Author: Type::INT8,
+    }
+
+    pub struct Recent {
+        Id: Type::INT8,
+    }
+}
+
+fn main() {
+    let _ = thorn::sql! {
+        SELECT Author.Name FROM Users AS Author INNER JOIN Posts ON Posts.Author = Author.Id
+    };
+
+    // subqueries can see the tables of enclosing queries
+    let _ = thorn::sql! {
+        SELECT Users.Name FROM Users
+        WHERE EXISTS (SELECT 1 FROM Posts WHERE Posts.Author = Users.Id)
+    };
+
+    let _ = thorn::sql! {
+        SELECT Users.Name FROM Users,
+            LATERAL (SELECT Posts.Id FROM Posts WHERE Posts.Author = Users.Id LIMIT 1)
+    };
+
+    let _ = thorn::sql! {
+        WITH Recent (Id) AS (SELECT Posts.Id FROM Posts WHERE Posts.Id > 10)
+        SELECT Users.Name FROM Users INNER JOIN Recent ON Recent.Id = Users.Id
+    };
+
+    let _ = thorn::sql! {
+        UPDATE Users SET (Name) = ("x") FROM Posts WHERE Posts.Author = Users.Id
+    };
+
+    let _ = thorn::sql! {
+        DELETE FROM Posts USING Users WHERE Posts.Author = Users.Id AND Users.Name = "x"
+    };
+}
@@ -42,7 +42,7 @@ fn build_operators() -> Result<String, std::fmt::Error> {
         "~", "||", "|", "^@", "^", "@@", "@", "@>", ">>", ">=", ">", "=", "<@", "<>", "<=", "<<", "<", ";", "/||",
         "/|", "/", "|/", "||/", "->>", "->", "-", ",", "+", "*", "&&", "&", "%", "#>>", "#>", "#", "!=", "!!",
         "!", "$$", "<<=", ">>=", "&<", "<&", "-|-", "@-@", "<->", "<<|", "|>>", "&<|", "|&>", "<^", ">^", "?#",
-        "?-", "?|", "?-|", "?||", "~=", "~*",
+        "?-", "?|", "?-|", "?||", "~=", "~*", "&>",
     ];
 
     // sort and order by longest first
@@ -0,0 +1,78 @@
+//! `#[derive(Literal)]` for newtypes and unit-only enums
+//!
+//! Generated code refers to `::thorn`, unless overridden with `#[thorn(crate = path::to::thorn)]`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Path};
+
+/// The path of the `thorn` crate, from `#[thorn(crate = ...)]`
+fn crate_path(input: &DeriveInput) -> syn::Result<Path> {
+    let mut krate = syn::parse_quote!(::thorn);
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("thorn")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unknown thorn attribute, expected `crate`"))
+            }
+        })?;
+    }
+
+    Ok(krate)
+}
+
+pub fn literal(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = crate_path(&input)?;
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+
+    let body =
+        match input.data {
+            Data::Struct(ref data) if data.fields.len() == 1 => {
+                let field = data.fields.iter().next().unwrap();
+                let ty = &field.ty;
+
+                let access = match field.ident {
+                    Some(ref ident) => quote!(#ident),
+                    None => quote!(0),
+                };
+
+                generics.make_where_clause().predicates.push(syn::parse_quote!(#ty: #krate::literal::Literal));
+
+                quote! {
+                    #[inline]
+                    fn write_literal(&self, w: &mut dyn ::std::fmt::Write, depth: usize) -> ::std::fmt::Result {
+                        #krate::literal::Literal::write_literal(&self.#access, w, depth)
+                    }
+
+                    #[inline]
+                    fn array_dims(&self) -> Option<Vec<usize>> {
+                        #krate::literal::Literal::array_dims(&self.#access)
+                    }
+                }
+            }
+            Data::Enum(ref data) if data.variants.iter().all(|v| matches!(v.fields, Fields::Unit)) => {
+                quote! {
+                    #[inline]
+                    fn write_literal(&self, w: &mut dyn ::std::fmt::Write, depth: usize) -> ::std::fmt::Result {
+                        #krate::literal::__write_enum(self, w, depth)
+                    }
+                }
+            }
+            _ => return Err(Error::new_spanned(
+                name,
+                "`Literal` can only be derived for structs with a single field or enums with only unit variants",
+            )),
+        };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::literal::Literal for #name #ty_generics #where_clause {
+            #body
+        }
+    })
+}
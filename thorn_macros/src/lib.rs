@@ -11,12 +11,46 @@ include!(concat!(env!("OUT_DIR"), "/codegen.rs"));
 
 #[proc_macro]
 pub fn __isql2(input: TokenStream) -> TokenStream {
-    syn::parse_macro_input!(input with do_parse).into()
+    let input = TokenStream2::from(input);
+    let krate = input.clone().into_iter().next();
+
+    match syn::parse::Parser::parse2(do_parse, input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => {
+            let err = err.into_compile_error();
+
+            // still return a query, so the closure around the macro does not add its own type errors
+            quote::quote! {
+                #err
+                return Ok(#krate::macros::Query::<Columns>::default());
+            }
+            .into()
+        }
+    }
+}
+
+/// Implements `thorn::literal::Literal` for a newtype by writing its only field, or for a unit-only enum
+/// declared with `enums!` as `'variant'::"schema"."enum_type"`.
+///
+/// When `thorn` is not a direct dependency, its path can be given with `#[thorn(crate = path::to::thorn)]`.
+#[proc_macro_derive(Literal, attributes(thorn))]
+pub fn derive_literal(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    derive::literal(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Declares the inert attributes allowed on `tables!` columns, such as `#[sensitive]`
+#[doc(hidden)]
+#[proc_macro_derive(__Columns, attributes(sensitive))]
+pub fn __columns(_input: TokenStream) -> TokenStream {
+    TokenStream::new()
 }
 
 fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
     let krate: Ident = input.parse()?;
     let writer = Ident::new("__thorn_query", Span::call_site());
+    let lints = lint::Lints::parse_attrs(input)?;
 
     let mut state = State {
         krate: krate.clone(),
@@ -27,11 +61,35 @@ fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
         cte: None,
         depth: 0,
         dynamic: false,
+        captures: false,
         params: Vec::new(),
+        source_tokens: Vec::new(),
+        joint: false,
+        chunk: Vec::new(),
+        chunks: Vec::new(),
+        lints,
+        checks: Vec::new(),
+        runtime_columns: 0,
     };
 
     let mut tokens = state.parse(input, &mut 0, false)?;
 
+    state.lints.check()?;
+
+    // names each `{col!}` may be qualified with, resolved from the tables in scope of it
+    for (idx, tables) in state.lints.runtime_columns().into_iter().enumerate() {
+        let scope = quote::format_ident!("__THORN_COLUMN_SCOPE_{idx}");
+
+        let entries = tables.iter().map(|(table, name)| {
+            let name = state.ident.ident(name).to_snake_case();
+            quote::quote! { (<#table as #krate::table::TableExt>::TYPENAME_SNAKE, #name) }
+        });
+
+        tokens.extend(quote::quote! {
+            const #scope: &[(&str, &'static str)] = &[#(#entries),*];
+        });
+    }
+
     let dynamic = state.dynamic;
     tokens.extend(quote::quote! {
         impl Columns {
@@ -41,14 +99,17 @@ fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
 
     //println!("{}", tokens.to_string());
 
-    // final flush, after trim
-    state.buffer.truncate(state.buffer.trim_end().len());
+    // final flush
     if !state.buffer.is_empty() {
+        state.emit_chunk(&mut tokens);
+
         let writer = &state.writer;
         let buffer = &state.buffer;
         tokens.extend(quote::quote! { #writer.write_str(#buffer); });
     }
 
+    let source_map = state.source_map_table();
+
     if let Some(first) = state.exports.first() {
         let mut buffer = state.ident;
 
@@ -83,9 +144,24 @@ fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
 
     let writer_ty = quote::quote! { #krate::macros::Query::<Columns> };
 
+    let location = quote::quote! {
+        #krate::macros::SourceLocation { file: file!(), line: line!(), column: column!() }, &__THORN_SOURCE_MAP
+    };
+
     if state.dynamic {
         tokens = quote::quote! {
-            let mut #writer = #writer_ty::default();
+            macro_rules! __thorn_param {
+                ($value:expr) => { ($value) as _ };
+            }
+
+            // never executed, only type-checked
+            macro_rules! __thorn_check {
+                ($check:expr) => { if false { $check; } };
+            }
+
+            #source_map
+
+            let mut #writer = #writer_ty::__new(#location);
 
             #tokens
 
@@ -93,18 +169,53 @@ fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
         };
     } else {
         let params = state.params.iter().map(|(v, _)| v);
+        let checks = &state.checks;
+
+        // static queries only need the parameter types to build the query text,
+        // so avoid evaluating the parameter expressions more than once
+        let build = quote::quote! {
+            macro_rules! __thorn_param {
+                ($value:expr) => { &#krate::macros::NULL_PARAM };
+            }
+
+            macro_rules! __thorn_check {
+                ($check:expr) => {};
+            }
+
+            let mut #writer = #writer_ty::__new(#location);
+
+            #tokens
+
+            Ok(#writer.into())
+        };
+
+        let get = if state.captures {
+            // the query may reference local variables, so it must be built in place
+            quote::quote! { let __thorn_cached = __QUERY.get_or_init(|| { #build }); }
+        } else {
+            quote::quote! {
+                fn __thorn_build() -> Result<#krate::macros::StaticQuery<Columns>, #krate::macros::SqlFormatError> {
+                    #build
+                }
+
+                #krate::__register_query!(__QUERY, __thorn_build);
+
+                let __thorn_cached = __QUERY.get_or_init(__thorn_build);
+            }
+        };
 
         tokens = quote::quote! {
             static __QUERY: std::sync::OnceLock<Result<#krate::macros::StaticQuery<Columns>, #krate::macros::SqlFormatError>>
                 = std::sync::OnceLock::new();
 
-            return match __QUERY.get_or_init(|| {
-                let mut #writer = #writer_ty::default();
+            #source_map
 
-                #tokens
+            #get
 
-                Ok(#writer.into())
-            }) {
+            // parameter expressions are only in scope here, never executed
+            if false { #(#checks;)* }
+
+            return match __thorn_cached {
                 Err(e) => Err(e.clone()),
                 Ok(q) => Ok(#writer_ty::__from_cached(q, vec![#(#params),*])),
             };
@@ -114,9 +225,19 @@ fn do_parse(input: ParseStream) -> syn::Result<TokenStream2> {
     Ok(tokens)
 }
 
+mod derive;
+// copy of `thorn::escape`
+#[allow(dead_code)]
+mod escape;
+mod lint;
 mod lit;
+mod scope;
+// copy of `thorn::spacing`
+mod spacing;
 //mod punct;
 
+use spacing::needs_space;
+
 const TRAILING_COMMA: &str = "Trailing commas are not allowed in SQL";
 
 use syn::{
@@ -172,7 +293,23 @@ struct State {
     cte: Option<Ident>,
     depth: usize,
     dynamic: bool,
+    /// Set when the query may reference local variables, which prevents building
+    /// static queries outside of their enclosing function.
+    captures: bool,
     params: Vec<(Box<syn::Expr>, Box<syn::Type>)>,
+    /// Source tokens referenced by the source map, as `(line, column, text)`
+    source_tokens: Vec<(usize, usize, String)>,
+    /// Set when the last token pushed was punctuation joined to the next token
+    joint: bool,
+    /// `(offset, token index)` pairs for tokens in the current buffer
+    chunk: Vec<(usize, usize)>,
+    /// All chunks of text written so far, indexed by the runtime source map
+    chunks: Vec<Vec<(usize, usize)>>,
+    lints: lint::Lints,
+    /// Compile-time checks of parameter types, see `param_check`
+    checks: Vec<TokenStream2>,
+    /// Number of `{col!}` runtime columns so far
+    runtime_columns: usize,
 }
 
 impl State {
@@ -180,57 +317,118 @@ impl State {
         self.ident.ident(ident)
     }
 
-    fn rewrite_spacing(&mut self) {
-        let Some(start) = self.buffer.len().checked_sub(2) else {
+    fn add_source_token(&mut self, span: Span, text: &str) -> usize {
+        let start = span.start();
+        self.source_tokens.push((start.line, start.column + 1, text.to_owned()));
+        self.source_tokens.len() - 1
+    }
+
+    /// Record that a source token starts at `offset` in the current buffer
+    fn record(&mut self, offset: usize, span: Span, text: &str) {
+        if !text.trim().is_empty() {
+            let token = self.add_source_token(span, text);
+            self.chunk.push((offset, token));
+        }
+    }
+
+    /// Create a chunk for text written at runtime, returning its index
+    fn new_chunk(&mut self, span: Span, text: &str) -> u32 {
+        let token = self.add_source_token(span, text);
+        self.chunks.push(vec![(0, token)]);
+        (self.chunks.len() - 1) as u32
+    }
+
+    fn emit_chunk(&mut self, out: &mut TokenStream2) {
+        if self.chunk.is_empty() {
             return;
-        };
+        }
+
+        self.chunks.push(std::mem::take(&mut self.chunk));
 
-        let mut chars = self.buffer.chars().rev();
+        let idx = (self.chunks.len() - 1) as u32;
+        let writer = &self.writer;
+        out.extend(quote::quote! { #writer.__source(#idx); });
+    }
 
-        let token = chars.next().unwrap();
-        let maybe_ws = chars.next().unwrap();
+    /// Map the next runtime write to the given source token, must be called after a flush.
+    fn mark(&mut self, out: &mut TokenStream2, span: Span, text: &str) {
+        self.mark_as(out, span, text, lint::Tok::Other);
+    }
+
+    fn mark_as(&mut self, out: &mut TokenStream2, span: Span, text: &str, tok: lint::Tok) {
+        let idx = self.new_chunk(span, text);
+        self.lints.push(tok, span);
+        let writer = &self.writer;
+        out.extend(quote::quote! { #writer.__source(#idx); });
+    }
 
-        if maybe_ws.is_whitespace() && matches!(token, ',' | ')' | ']') {
-            self.buffer.truncate(start);
-            self.buffer.push(token);
+    fn source_map_table(&self) -> TokenStream2 {
+        let krate = &self.krate;
+
+        let tokens = self.source_tokens.iter().map(|(line, column, text)| {
+            let (line, column) = (*line as u32, *column as u32);
+            quote::quote! { #krate::source_map::SourceToken { line: #line, column: #column, text: #text } }
+        });
+
+        let chunks = self.chunks.iter().map(|chunk| {
+            let entries = chunk.iter().map(|&(offset, token)| {
+                let (offset, token) = (offset as u32, token as u32);
+                quote::quote! { (#offset, #token) }
+            });
+
+            quote::quote! { &[#(#entries),*] }
+        });
+
+        quote::quote! {
+            static __THORN_SOURCE_MAP: #krate::source_map::SourceMapTable = #krate::source_map::SourceMapTable {
+                tokens: &[#(#tokens),*],
+                chunks: &[#(#chunks),*],
+            };
         }
     }
 
-    fn push(&mut self, tokens: impl ToTokens) {
-        use std::fmt::Write;
+    /// Separate the next token from the buffer with a space, if needed.
+    fn separate(&mut self, next: &str) {
+        if !std::mem::take(&mut self.joint) && needs_space(&self.buffer, next) {
+            self.buffer.push(' ');
+        }
+    }
 
+    fn push(&mut self, tokens: impl ToTokens) {
         for t in tokens.into_token_stream() {
-            let old_len = self.buffer.len();
-            write!(self.buffer, "{}", t).unwrap();
+            let text = t.to_string();
 
-            // single-byte token, check for punctuation rewrite
-            if old_len + 1 == self.buffer.len() {
-                self.rewrite_spacing();
-            }
+            self.separate(&text);
+            self.record(self.buffer.len(), t.span(), &text);
+            self.lints.text(&text, t.span());
+            self.buffer.push_str(&text);
 
-            if !matches!(t, TokenTree::Punct(ref p) if p.spacing() == Spacing::Joint) {
-                self.buffer.push_str(" ")
-            }
+            self.joint = matches!(t, TokenTree::Punct(ref p) if p.spacing() == Spacing::Joint);
         }
     }
 
+    fn push_str_spanned(&mut self, token: impl AsRef<str>, span: Span) {
+        let token = token.as_ref();
+        self.separate(token);
+        self.record(self.buffer.len(), span, token);
+        self.lints.text(token, span);
+        self.buffer.push_str(token);
+    }
+
     fn push_str(&mut self, token: impl AsRef<str>) {
         let token = token.as_ref();
+        self.separate(token);
+        self.lints.text(token, Span::call_site());
         self.buffer.push_str(token);
-        if token.len() == 1 {
-            self.rewrite_spacing();
-        }
-        if !matches!(token, "(" | "[" | "::") {
-            self.buffer.push_str(" ");
-        }
     }
 
     fn flush(&mut self, out: &mut TokenStream2) {
+        self.joint = false;
+
         if !self.buffer.is_empty() {
-            let mut buffer = std::mem::take(&mut self.buffer);
-            if !(buffer.ends_with([' ', '(', '[']) || buffer.ends_with("::")) {
-                buffer.push_str(" ");
-            }
+            let buffer = std::mem::take(&mut self.buffer);
+            self.emit_chunk(out);
+
             let writer = &self.writer;
             out.extend(quote::quote! { #writer.write_str(#buffer); });
         }
@@ -255,10 +453,11 @@ impl State {
     }
 
     fn push_if_keyword(&mut self, ident: &Ident) -> bool {
+        let span = ident.span();
         let ident = self.ident.take_ident(ident);
 
         if KEYWORDS.contains(&ident) {
-            self.push_str(ident);
+            self.push_str_spanned(ident, span);
             return true;
         }
 
@@ -275,14 +474,14 @@ impl State {
         }
 
         let inner;
-        syn::parenthesized!(inner in input);
+        let paren = syn::parenthesized!(inner in input);
 
         self.cte = Some(table.clone());
-        self.push_str("(");
+        self.push_str_spanned("(", paren.span.open());
         self.depth += 1;
         self.parse(&inner, &mut 0, false)?.to_tokens(out);
         self.depth -= 1;
-        self.push_str(")");
+        self.push_str_spanned(")", paren.span.close());
         self.cte = None;
 
         Ok(())
@@ -348,6 +547,7 @@ impl State {
         self.push(LitStr::new(&alias_name, alias.span()));
 
         out.extend(quote::quote! { type #alias = #table; });
+        self.lints.push(lint::Tok::Alias(alias.clone()), alias.span());
 
         Ok(alias)
     }
@@ -454,9 +654,10 @@ impl State {
                 let table_name = self.ident(&ident).to_snake_case();
 
                 self.flush(out);
+                let tok = lint::Tok::Column(ident.clone());
+                self.mark_as(out, ident.span(), &format!("{ident}.{column}"), tok);
                 let writer = &self.writer;
                 out.extend(quote::quote! { #writer.write_column(#ident::#column, #table_name)?; });
-                self.push_str(""); // empty space after column name
 
                 // Table.Column AS @_
                 //
@@ -495,12 +696,14 @@ impl State {
 
     fn write_table(&mut self, out: &mut TokenStream2, table: &Ident) {
         self.flush(out);
+        self.mark_as(out, table.span(), &table.to_string(), lint::Tok::Table(table.clone()));
         let writer = &self.writer;
         out.extend(quote::quote! { #writer.write_table::<#table>()?; })
     }
 
     fn write_column_name(&mut self, out: &mut TokenStream2, table: &Ident, column: &Ident) {
         self.flush(out);
+        self.mark(out, column.span(), &column.to_string());
         let writer = &self.writer;
         out.extend(quote::quote! { #writer.write_column_name(#table::#column)?; });
     }
@@ -634,7 +837,7 @@ impl State {
                     self.push(input.parse::<Ident>()?);
 
                     let inner;
-                    syn::parenthesized!(inner in input);
+                    let paren = syn::parenthesized!(inner in input);
 
                     let as_token: kw::AS = input.parse()?;
                     let alias: Ident = input.parse()?;
@@ -643,11 +846,11 @@ impl State {
                     let old_cte = self.cte.replace(alias.clone());
 
                     // typical parenthesis
-                    self.push_str("(");
+                    self.push_str_spanned("(", paren.span.open());
                     self.depth += 1;
                     self.parse(&inner, &mut 0, false)?.to_tokens(out);
                     self.depth -= 1;
-                    self.push_str(")");
+                    self.push_str_spanned(")", paren.span.close());
 
                     self.cte = old_cte;
 
@@ -667,15 +870,16 @@ impl State {
                     let mut num_commas = 0;
 
                     self.flush(out);
+                    self.mark(out, ident.span(), &ident.to_string());
                     let writer = &self.writer;
                     out.extend(quote::quote! { #writer.write_func::<#ident>(); });
 
                     // like () handling, but counts the commas in the subtree
-                    self.push_str("(");
+                    self.push_str_spanned("(", parens.span.open());
                     self.depth += 1;
                     self.parse(&args, &mut num_commas, false)?.to_tokens(out);
                     self.depth -= 1;
-                    self.push_str(")");
+                    self.push_str_spanned(")", parens.span.close());
 
                     // if we have arguments, commas + 1 is the number of arguments
                     num_commas += has_args as usize;
@@ -692,11 +896,13 @@ impl State {
 
                 _ if input.peek(Token![match]) => {
                     self.flush(out);
+                    self.lints.push(lint::Tok::Branch, input.span());
                     parse_match(input, self)?.to_tokens(out);
                 }
 
                 _ if input.peek(Token![if]) => {
                     self.flush(out);
+                    self.lints.push(lint::Tok::Branch, input.span());
                     parse_if(input, self)?.to_tokens(out);
                 }
 
@@ -706,6 +912,7 @@ impl State {
                         && (input.peek3(Token![for]) || input.peek3(kw::join))) =>
                 {
                     self.flush(out);
+                    self.lints.push(lint::Tok::Branch, input.span());
                     parse_for(input, self)?.to_tokens(out, self);
                 }
 
@@ -719,16 +926,18 @@ impl State {
                 }
 
                 _ if is_macro(input) => {
+                    self.lints.push(lint::Tok::Opaque, input.span());
                     input.parse::<syn::Stmt>()?.to_tokens(out);
+                    self.captures = true;
                 }
 
                 _ if input.peek(Token![const]) && input.peek2(Brace) => {
                     let block = input.parse::<syn::PatConst>()?;
 
                     self.flush(out);
+                    self.mark(out, block.const_token.span, &block.to_token_stream().to_string());
                     let writer = &self.writer;
                     out.extend(quote::quote! { #writer.write_literal(#block)?; });
-                    self.push_str(""); // empty space after literal
                 }
 
                 // arbitrary const rust blocks
@@ -746,45 +955,74 @@ impl State {
 
                 // SQL literals
                 _ if input.peek(syn::Lit) => {
-                    lit::push_lit(lit::parse_lit(input)?, self);
+                    lit::push_lit(lit::parse_lit(input)?, self)?;
                 }
 
                 // parameters #{&value as Type::INT4}
                 _ if input.peek(Token![#]) && input.peek2(Brace) => {
-                    let _pound_token: Token![#] = input.parse()?;
+                    let pound_token: Token![#] = input.parse()?;
 
                     let inner;
                     syn::braced!(inner in input);
                     let syn::ExprCast { expr, ty, .. } = inner.parse::<syn::ExprCast>()?;
 
+                    // single-segment paths may be local variables
+                    if !matches!(*ty, syn::Type::Path(ref p) if p.path.segments.len() > 1) {
+                        self.captures = true;
+                    }
+
+                    // types that depend on generic parameters can't be named from a nested item,
+                    // and may differ between instantiations, so they can't be cached either
+                    if is_generic_path(&ty) {
+                        self.dynamic = true;
+                    }
+
                     self.flush(out);
+                    self.mark(out, pound_token.span, &format!("#{{{}}}", expr.to_token_stream()));
                     let writer = &self.writer;
-                    out.extend(
-                        quote::quote! { #writer.param::<{Columns::IS_DYNAMIC}>((#expr) as _, #ty.into())?; },
-                    );
+                    let krate = &self.krate;
+
+                    // any `Into<pg::Type>` is accepted, and columns may also mark the parameter as sensitive
+                    out.extend(quote::quote! {{
+                        use #krate::macros::{ColumnSensitivity as _, TypeSensitivity as _};
+
+                        let __thorn_ty = #ty;
+                        let __thorn_sensitive = (&#krate::macros::Sensitivity(&__thorn_ty)).__is_sensitive();
+
+                        #writer.__param::<{Columns::IS_DYNAMIC}>(__thorn_param!(#expr), __thorn_ty.into(), __thorn_sensitive)?;
+                    }});
+
+                    if let Some(check) = param_check(&self.krate, &expr, &ty) {
+                        out.extend(quote::quote! { __thorn_check!(#check); });
+                        self.checks.push(check);
+                    }
+
                     self.params.push((expr, ty));
-                    self.push_str(""); // space after param
                 }
 
                 _ if input.peek(Token![@]) && input.peek2(Brace) => {
-                    let _at_token: Token![@] = input.parse()?;
+                    let at_token: Token![@] = input.parse()?;
                     let block: syn::Block = input.parse()?;
 
                     self.flush(out);
+                    self.mark(out, at_token.span, &format!("@{}", block.to_token_stream()));
+                    self.lints.push(lint::Tok::Opaque, at_token.span);
                     let writer = &self.writer;
                     out.extend(quote::quote! { write!(#writer, "{}", #block)?; });
+                    self.captures = true;
                 }
 
                 // arbitrary Rust expressions ${x += 1;}
                 _ if input.peek(Token![$]) && input.peek2(Brace) => {
                     let _bang: Token![$] = input.parse()?;
                     input.parse::<syn::Block>()?.to_tokens(out);
+                    self.captures = true;
                 }
 
                 // SQL type casting
                 _ if input.peek(Token![::]) => {
-                    let _colon: Token![::] = input.parse()?;
-                    self.push_str("::");
+                    let colon: Token![::] = input.parse()?;
+                    self.push_str_spanned("::", colon.spans[0]);
 
                     if input.peek(Ident) {
                         let ident: Ident = input.parse()?;
@@ -799,22 +1037,26 @@ impl State {
                         let ty_ident = Ident::new(&ty, ident.span());
 
                         self.flush(out);
+                        self.mark(out, ident.span(), &ident.to_string());
                         let writer = &self.writer;
                         out.extend(quote::quote! { #writer.write_str(pg::Type::#ty_ident.name()); });
-
-                        // cheat lack of space after this write_str by pushing an empty string
-                        self.push_str("");
                     } else if input.peek(Brace) {
                         let block: syn::Block = input.parse()?;
 
                         self.flush(out);
+                        self.mark(out, block.brace_token.span.open(), &block.to_token_stream().to_string());
                         let writer = &self.writer;
-                        out.extend(quote::quote! { write!(#writer, "{} ", pg::Type::from(#block))?; });
+                        out.extend(quote::quote! { write!(#writer, "{}", pg::Type::from(#block))?; });
+                        self.captures = true;
                     }
                 }
 
                 // statements
                 _ if is_stmt(input) => {
+                    if input.peek(Token![let]) {
+                        self.captures = true;
+                    }
+
                     input.parse::<syn::Stmt>()?.to_tokens(out);
                 }
 
@@ -823,14 +1065,36 @@ impl State {
                     return Err(input.error("Unexpected Rust keyword"));
                 }
 
+                // {col!}, runtime columns
+                _ if input.peek(Brace) && is_column_block(input) => {
+                    let inner;
+                    let brace = syn::braced!(inner in input);
+
+                    let mut tokens: Vec<TokenTree> = inner.parse::<TokenStream2>()?.into_iter().collect();
+                    tokens.pop(); // !
+
+                    let expr: Expr = syn::parse2(tokens.into_iter().collect())?;
+
+                    self.flush(out);
+                    let text = format!("{{{}!}}", expr.to_token_stream());
+                    self.mark_as(out, brace.span.open(), &text, lint::Tok::RuntimeColumn);
+                    let writer = &self.writer;
+                    let scope = quote::format_ident!("__THORN_COLUMN_SCOPE_{}", self.runtime_columns);
+                    out.extend(quote::quote! { #writer.write_column_ident(#expr, #scope)?; });
+
+                    self.runtime_columns += 1;
+
+                    self.dynamic = true;
+                }
+
                 // { ... }, runtime literals
                 _ if input.peek(Brace) => {
                     let expr = input.parse::<syn::Block>()?;
 
                     self.flush(out);
+                    self.mark(out, expr.brace_token.span.open(), &expr.to_token_stream().to_string());
                     let writer = &self.writer;
                     out.extend(quote::quote! { #writer.write_literal(#expr)?; });
-                    self.push_str(""); // empty space after literal
 
                     self.dynamic = true;
                 }
@@ -838,25 +1102,25 @@ impl State {
                 // (...)
                 _ if input.peek(Paren) => {
                     let inner;
-                    syn::parenthesized!(inner in input);
+                    let paren = syn::parenthesized!(inner in input);
 
-                    self.push_str("(");
+                    self.push_str_spanned("(", paren.span.open());
                     self.depth += 1;
                     self.parse(&inner, &mut 0, false)?.to_tokens(out);
                     self.depth -= 1;
-                    self.push_str(")");
+                    self.push_str_spanned(")", paren.span.close());
                 }
 
                 // [...]
                 _ if input.peek(Bracket) => {
                     let inner;
-                    syn::bracketed!(inner in input);
+                    let bracket = syn::bracketed!(inner in input);
 
-                    self.push_str("[");
+                    self.push_str_spanned("[", bracket.span.open());
                     self.depth += 1;
                     self.parse(&inner, &mut 0, false)?.to_tokens(out);
                     self.depth -= 1;
-                    self.push_str("]");
+                    self.push_str_spanned("]", bracket.span.close());
                 }
 
                 // detect trailing commas
@@ -873,8 +1137,9 @@ impl State {
 
                 _ => {
                     // attempt to parse known SQL operators
+                    let span = input.span();
                     if let Some(op) = parse_sql_operator(input)? {
-                        self.push_str(op);
+                        self.push_str_spanned(op, span);
                     } else {
                         // passthrough as text
                         self.push(input.parse::<proc_macro2::TokenTree>()?);
@@ -904,6 +1169,67 @@ impl State {
     }
 }
 
+/// For parameters typed by a column, `#{&value as Table::Column}`, checks the value against
+/// the Rust type of the column, if given in `tables!`.
+///
+/// Whether the path is a column is only known from its type, so any other path, such as `Type::INT8`
+/// or a user type implementing `Into<pg::Type>`, is left unchecked.
+fn param_check(krate: &Ident, expr: &Expr, ty: &syn::Type) -> Option<TokenStream2> {
+    let syn::Type::Path(ref path) = *ty else { return None };
+
+    if path.qself.is_some() {
+        return None;
+    }
+
+    let column = path.path.segments.last()?;
+
+    if !column.arguments.is_none() {
+        return None;
+    }
+
+    let name = column.ident.to_string();
+
+    Some(quote::quote! {{
+        use #krate::table::{CheckColumnElements as _, CheckColumnParam as _, SkipColumnParam as _};
+
+        (&&&#krate::table::ParamCheck::<_, { #krate::table::__column_hash(#name) }, _>::new(&#ty, #expr)).__check(#expr)
+    }})
+}
+
+/// Whether a parameter type may depend on generic parameters, as in `Self::ID_TY`, `T::ID_TY`
+/// or `<T as Trait>::ID_TY`.
+///
+/// Generic parameters can't be resolved here, so they are recognized by convention,
+/// as all-uppercase names like `T` or `DB`.
+fn is_generic_path(ty: &syn::Type) -> bool {
+    let syn::Type::Path(ref path) = *ty else { return false };
+
+    if path.qself.is_some() {
+        return true;
+    }
+
+    if path.path.segments.len() < 2 {
+        return false;
+    }
+
+    let name = path.path.segments[0].ident.to_string();
+
+    name == "Self"
+        || (name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+}
+
+/// `{expr!}`
+fn is_column_block(input: ParseStream) -> bool {
+    let Ok(TokenTree::Group(group)) = input.fork().parse::<TokenTree>() else {
+        return false;
+    };
+
+    let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+
+    tokens.len() > 1 && matches!(tokens.last(), Some(TokenTree::Punct(p)) if p.as_char() == '!')
+}
+
 fn is_rust_keyword(input: ParseStream) -> bool {
     input.peek(Ident::peek_any) && !input.peek(Ident)
 }
@@ -971,7 +1297,7 @@ struct If {
 struct For {
     label: Option<syn::Label>,
     for_token: Token![for],
-    joiner: Option<syn::LitStr>,
+    joiner: Option<(syn::LitStr, u32)>,
     pat: Box<syn::Pat>,
     in_token: Token![in],
     expr: Box<Expr>,
@@ -1132,14 +1458,17 @@ fn parse_for(input: ParseStream, state: &mut State) -> syn::Result<For> {
     let for_token: Token![for] = if input.peek(kw::join) {
         let join_token = input.parse::<kw::join>()?;
 
-        joiner = Some(if input.peek(Paren) {
+        let lit: syn::LitStr = if input.peek(Paren) {
             let joiner_input;
             syn::parenthesized!(joiner_input in input);
 
             joiner_input.parse()?
         } else {
             syn::LitStr::new(",", join_token.span)
-        });
+        };
+
+        let chunk = state.new_chunk(lit.span(), &lit.value());
+        joiner = Some((lit, chunk));
 
         Token![for](join_token.span)
     } else {
@@ -1193,10 +1522,10 @@ impl For {
         expr.to_tokens(tokens);
 
         brace_token.surround(tokens, |tokens| {
-            if let Some(ref joiner) = joiner {
+            if let Some((ref joiner, chunk)) = *joiner {
                 let writer = &state.writer;
                 tokens.extend(quote::quote! {
-                    if !__first { #writer.write_str(#joiner); }
+                    if !__first { #writer.__source(#chunk); #writer.write_str(#joiner); }
                     __first = false;
                 });
             }
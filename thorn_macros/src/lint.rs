@@ -0,0 +1,275 @@
+//! Compile-time checks for statements that are almost always mistakes.
+//!
+//! The parser records a simplified copy of the SQL it produces, which is split into statements
+//! and checked once the whole macro has been parsed. Lints can be disabled with an inner attribute
+//! at the start of the macro, `#![allow(missing_where)]`, and queries expected to return
+//! at most one row are marked with `#![single]`.
+
+use proc_macro2::Span;
+use syn::{parse::ParseStream, Attribute, Error, Ident, Meta};
+
+const LINTS: &[&str] = &["missing_where", "column_count", "missing_limit", "out_of_scope"];
+
+pub enum Tok {
+    Keyword(String),
+    Open,
+    Close,
+    Comma,
+    Semi,
+    /// Identifiers, literals, parameters and column names
+    Other,
+    Table(Ident),
+    /// `AS Alias` for the preceding table
+    Alias(Ident),
+    /// `Table.Column` reference to a table
+    Column(Ident),
+    /// `{col!}` column chosen at runtime
+    RuntimeColumn,
+    /// Start of an `if`, `match` or `for` block, whose bodies are recorded as if they were all written
+    Branch,
+    /// Arbitrary text written at runtime
+    Opaque,
+}
+
+#[derive(Default)]
+pub struct Lints {
+    single: bool,
+    missing_where: bool,
+    column_count: bool,
+    missing_limit: bool,
+    out_of_scope: bool,
+    tokens: Vec<(Tok, Span)>,
+}
+
+enum Node<'a> {
+    Tok(&'a Tok, Span),
+    Group(Vec<Node<'a>>, Span),
+}
+
+impl Lints {
+    /// Parse `#![single]` and `#![allow(...)]` attributes at the start of the macro
+    pub fn parse_attrs(input: ParseStream) -> syn::Result<Lints> {
+        let mut lints = Lints {
+            missing_where: true,
+            column_count: true,
+            missing_limit: true,
+            out_of_scope: true,
+            ..Lints::default()
+        };
+
+        for attr in Attribute::parse_inner(input)? {
+            match attr.meta {
+                Meta::Path(ref path) if path.is_ident("single") => lints.single = true,
+                Meta::List(ref list) if list.path.is_ident("allow") => {
+                    list.parse_nested_meta(|meta| {
+                        match meta.path.get_ident().map(|ident| ident.to_string()).as_deref() {
+                            Some("missing_where") => lints.missing_where = false,
+                            Some("column_count") => lints.column_count = false,
+                            Some("missing_limit") => lints.missing_limit = false,
+                            Some("out_of_scope") => lints.out_of_scope = false,
+                            _ => {
+                                return Err(
+                                    meta.error(format!("Unknown lint, expected one of: {}", LINTS.join(", ")))
+                                )
+                            }
+                        }
+
+                        Ok(())
+                    })?;
+                }
+                _ => return Err(Error::new_spanned(attr, "Expected `#![single]` or `#![allow(...)]`")),
+            }
+        }
+
+        Ok(lints)
+    }
+
+    pub fn push(&mut self, tok: Tok, span: Span) {
+        self.tokens.push((tok, span));
+    }
+
+    /// Record SQL text pushed at compile time
+    pub fn text(&mut self, text: &str, span: Span) {
+        let tok = match text {
+            "(" | "[" => Tok::Open,
+            ")" | "]" => Tok::Close,
+            "," => Tok::Comma,
+            ";" => Tok::Semi,
+            _ if super::KEYWORDS.contains(text) => Tok::Keyword(text.to_owned()),
+            _ => Tok::Other,
+        };
+
+        self.push(tok, span);
+    }
+
+    /// Tables visible to each `{col!}`, see [`crate::scope::runtime_columns`]
+    pub fn runtime_columns(&self) -> Vec<Vec<(Ident, Ident)>> {
+        crate::scope::runtime_columns(&self.tokens)
+    }
+
+    pub fn check(&self) -> syn::Result<()> {
+        let mut stack = vec![(Vec::new(), Span::call_site())];
+
+        for (tok, span) in &self.tokens {
+            match tok {
+                Tok::Open => stack.push((Vec::new(), *span)),
+                Tok::Close if stack.len() > 1 => {
+                    let (nodes, open) = stack.pop().unwrap();
+                    stack.last_mut().unwrap().0.push(Node::Group(nodes, open));
+                }
+                _ => stack.last_mut().unwrap().0.push(Node::Tok(tok, *span)),
+            }
+        }
+
+        let mut errors = Vec::new();
+
+        let (root, _) = stack.swap_remove(0);
+        self.check_group(&root, true, &mut errors);
+
+        if self.out_of_scope {
+            crate::scope::check(&self.tokens, &mut errors);
+        }
+
+        let mut errors = errors.into_iter();
+
+        match errors.next() {
+            Some(mut err) => {
+                errors.for_each(|e| err.combine(e));
+                Err(err)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn check_group(&self, nodes: &[Node], top: bool, errors: &mut Vec<Error>) {
+        for stmt in nodes.split(|node| matches!(node, Node::Tok(Tok::Semi, _))) {
+            self.check_stmt(stmt, top, errors);
+        }
+
+        for node in nodes {
+            if let Node::Group(ref inner, _) = *node {
+                self.check_group(inner, false, errors);
+            }
+        }
+    }
+
+    fn check_stmt(&self, stmt: &[Node], top: bool, errors: &mut Vec<Error>) {
+        let Some((verb, span, rest)) = main_verb(stmt) else {
+            return;
+        };
+
+        let opaque = rest.iter().any(|node| matches!(node, Node::Tok(Tok::Opaque, _)));
+
+        match verb {
+            "UPDATE" | "DELETE" if self.missing_where && !opaque && !has_keyword(rest, &["WHERE"]) => {
+                errors.push(Error::new(
+                    span,
+                    format!(
+                        "{verb} without WHERE affects every row in the table, \
+                         add a WHERE clause or `#![allow(missing_where)]` if intended"
+                    ),
+                ));
+            }
+            "SELECT"
+                if self.single
+                    && self.missing_limit
+                    && top
+                    && !opaque
+                    && has_keyword(rest, &["FROM"])
+                    && !has_keyword(rest, &["LIMIT", "FETCH"]) =>
+            {
+                errors.push(Error::new(
+                    span,
+                    "Query is marked `single` but may return multiple rows, \
+                     add a LIMIT or `#![allow(missing_limit)]`",
+                ));
+            }
+            "INSERT" if self.column_count => check_values(rest, errors),
+            _ => {}
+        }
+    }
+}
+
+/// Find the statement keyword, skipping any `WITH` clause
+fn main_verb<'a, 'b>(stmt: &'b [Node<'a>]) -> Option<(&'a str, Span, &'b [Node<'a>])> {
+    let mut in_with = false;
+
+    for (i, node) in stmt.iter().enumerate() {
+        match *node {
+            Node::Tok(Tok::Keyword(ref kw), span) => match kw.as_str() {
+                "SELECT" | "INSERT" | "UPDATE" | "DELETE" | "VALUES" => return Some((kw, span, &stmt[i + 1..])),
+                "WITH" if i == 0 => in_with = true,
+                _ if in_with => {}
+                _ => return None,
+            },
+            Node::Tok(Tok::Branch | Tok::Opaque, _) => return None,
+            _ if in_with => {}
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+fn has_keyword(nodes: &[Node], keywords: &[&str]) -> bool {
+    nodes
+        .iter()
+        .any(|node| matches!(*node, Node::Tok(Tok::Keyword(ref kw), _) if keywords.contains(&kw.as_str())))
+}
+
+/// Number of comma-separated items in a group, or `None` if the group contains control flow
+fn count_items(nodes: &[Node]) -> Option<usize> {
+    if nodes.iter().any(|node| matches!(node, Node::Tok(Tok::Branch | Tok::Opaque, _))) {
+        return None;
+    }
+
+    Some(nodes.split(|node| matches!(node, Node::Tok(Tok::Comma, _))).filter(|item| !item.is_empty()).count())
+}
+
+/// `INSERT INTO Table (Col, Col) VALUES (Value, Value), ...`
+fn check_values(rest: &[Node], errors: &mut Vec<Error>) {
+    let mut nodes = rest.iter();
+
+    if !matches!(nodes.next(), Some(Node::Tok(Tok::Keyword(kw), _)) if kw == "INTO") {
+        return;
+    }
+
+    // the first group after the table name and alias is the column list
+    let columns = loop {
+        match nodes.next() {
+            Some(Node::Group(columns, _)) => break columns,
+            Some(Node::Tok(Tok::Table(_) | Tok::Alias(_) | Tok::Other, _)) => {}
+            Some(Node::Tok(Tok::Keyword(kw), _)) if kw == "AS" => {}
+            _ => return,
+        }
+    };
+
+    let Some(num_columns) = count_items(columns) else {
+        return;
+    };
+
+    if !matches!(nodes.next(), Some(Node::Tok(Tok::Keyword(kw), _)) if kw == "VALUES") {
+        return;
+    }
+
+    for node in nodes {
+        match *node {
+            Node::Group(ref values, span) => {
+                if let Some(num_values) = count_items(values) {
+                    if num_values != num_columns {
+                        errors.push(Error::new(
+                            span,
+                            format!(
+                                "INSERT has {num_columns} target column{} but {num_values} value{}",
+                                if num_columns == 1 { "" } else { "s" },
+                                if num_values == 1 { "" } else { "s" },
+                            ),
+                        ));
+                    }
+                }
+            }
+            Node::Tok(Tok::Comma | Tok::Branch, _) => {}
+            _ => break,
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use std::fmt::{self, Write};
+use std::fmt::Write;
 
 use proc_macro2::Ident;
 use syn::{parse::ParseStream, Error, Lit};
 
+use crate::escape;
+
 pub fn parse_lit(input: ParseStream) -> syn::Result<Lit> {
     let lit = input.parse()?;
     match lit {
@@ -11,52 +13,31 @@ pub fn parse_lit(input: ParseStream) -> syn::Result<Lit> {
     }
 }
 
-pub(crate) fn push_lit(lit: Lit, state: &mut super::State) {
+pub(crate) fn push_lit(lit: Lit, state: &mut super::State) -> syn::Result<()> {
+    let span = lit.span();
+    let mut buf = String::new();
+
     match lit {
-        lit @ (Lit::Int(_) | Lit::Float(_)) => state.push(lit),
+        lit @ (Lit::Int(_) | Lit::Float(_)) => {
+            state.push(lit);
+            return Ok(());
+        }
         Lit::Bool(b) => {
             state.push(Ident::new(if b.value { "TRUE" } else { "FALSE" }, b.span));
+            return Ok(());
         }
-        Lit::Str(s) => state.push_str({
-            let mut buf = String::new();
-            write_escaped_string_quoted(&s.value(), &mut buf).unwrap();
-            buf
-        }),
-        // https://www.postgresql.org/docs/15/datatype-binary.html#id-1.5.7.12.9
-        Lit::ByteStr(s) => state.push_str({
-            let mut buf = "'\\x".to_owned();
-            for byte in s.value() {
-                write!(buf, "{byte:0X}").unwrap();
+        Lit::Str(s) => {
+            if escape::write_string(&s.value(), &mut buf).is_err() {
+                return Err(Error::new(span, "PostgreSQL strings cannot contain NUL characters"));
             }
-            buf.push_str("'");
-            buf
-        }),
-        Lit::Byte(b) => state.push_str({
-            let mut buf = "x'".to_owned();
-            write!(buf, "{:X}'", b.value()).unwrap();
-            buf
-        }),
+        }
+        Lit::ByteStr(s) => escape::write_bytea(&s.value(), &mut buf).unwrap(),
+        // bit string constant
+        Lit::Byte(b) => write!(buf, "x'{:02X}'", b.value()).unwrap(),
         _ => unimplemented!(),
     }
-}
-
-pub fn escape_string(string: &str) -> String {
-    string
-        .replace('\\', "\\\\")
-        .replace('"', "\\\"")
-        .replace('\'', "\\'")
-        .replace('\0', "\\0")
-        .replace('\x08', "\\b")
-        .replace('\x09', "\\t")
-        .replace('\x1a', "\\z")
-        .replace('\n', "\\n")
-        .replace('\r', "\\r")
-}
 
-pub fn write_escaped_string_quoted(string: &str, mut w: impl Write) -> fmt::Result {
-    let escaped = escape_string(string);
+    state.push_str_spanned(buf, span);
 
-    w.write_str(if escaped.find('\\').is_some() { "E'" } else { "'" })?;
-    w.write_str(&escaped)?;
-    w.write_str("'")
+    Ok(())
 }
@@ -0,0 +1,202 @@
+//! Checks that `Table.Column` references only name tables in scope of their (sub)query.
+//!
+//! Tables are brought into scope by `FROM`, `JOIN`, `USING`, `UPDATE` and `INSERT INTO`, where an alias
+//! replaces the original name. Subqueries can reference the tables of enclosing queries, except for
+//! `WITH` bodies and non-`LATERAL` subqueries in `FROM`, which cannot see their own query's tables.
+//!
+//! The same scopes decide which name a `{col!}` runtime column is qualified with.
+
+use proc_macro2::Span;
+use syn::{Error, Ident};
+
+use crate::lint::Tok;
+
+struct Scope {
+    parent: Option<usize>,
+    /// Cannot see the tables of the parent query
+    isolated: bool,
+    /// Contains text written at runtime, which may bring anything into scope
+    opaque: bool,
+    /// Whether the current clause brings tables into scope
+    intro: bool,
+    tables: Vec<Ident>,
+    renamed: Vec<(Ident, Ident)>,
+}
+
+impl Scope {
+    fn new(parent: Option<usize>, isolated: bool) -> Scope {
+        Scope {
+            parent,
+            isolated,
+            opaque: false,
+            intro: false,
+            tables: Vec::new(),
+            renamed: Vec::new(),
+        }
+    }
+}
+
+fn starts_query(kw: &str) -> bool {
+    matches!(kw, "SELECT" | "WITH" | "VALUES" | "INSERT" | "UPDATE" | "DELETE")
+}
+
+/// Splits the tokens into scopes, returning them along with the scope of each `Table.Column` and `{col!}`
+fn scopes(tokens: &[(Tok, Span)]) -> (Vec<Scope>, Vec<(usize, &Tok)>) {
+    let mut scopes = vec![Scope::new(None, false)];
+    let mut refs: Vec<(usize, &Tok)> = Vec::new();
+
+    // for each open group, the scope it created, if any
+    let mut groups: Vec<Option<usize>> = vec![Some(0)];
+    let mut prev: Option<&Tok> = None;
+
+    for (i, (tok, _)) in tokens.iter().enumerate() {
+        let current = groups.iter().rev().find_map(|&scope| scope).unwrap_or(0);
+
+        match tok {
+            Tok::Open => {
+                let next = tokens[i + 1..].iter().find(|(tok, _)| !matches!(tok, Tok::Branch));
+
+                groups.push(match next {
+                    Some((Tok::Keyword(kw), _)) if starts_query(kw) => {
+                        let isolated = match prev {
+                            Some(Tok::Keyword(kw)) if kw == "AS" || kw == "MATERIALIZED" => true,
+                            Some(Tok::Keyword(kw)) if kw == "LATERAL" => false,
+                            _ => scopes[current].intro,
+                        };
+
+                        scopes.push(Scope::new(Some(current), isolated));
+                        Some(scopes.len() - 1)
+                    }
+                    _ => None,
+                });
+            }
+            Tok::Close if groups.len() > 1 => {
+                groups.pop();
+            }
+            Tok::Semi => {
+                let scope = &scopes[current];
+                let sibling = Scope::new(scope.parent, scope.isolated);
+
+                scopes.push(sibling);
+
+                if let Some(group) = groups.iter_mut().rev().find(|scope| scope.is_some()) {
+                    *group = Some(scopes.len() - 1);
+                }
+            }
+            Tok::Keyword(kw) => match kw.as_str() {
+                "FROM" | "JOIN" | "USING" | "UPDATE" | "INTO" => scopes[current].intro = true,
+                "SELECT" | "WHERE" | "ON" | "SET" | "GROUP" | "ORDER" | "HAVING" | "RETURNING" | "VALUES"
+                | "LIMIT" | "OFFSET" | "WITH" | "WINDOW" | "UNION" | "INTERSECT" | "EXCEPT" | "DO" | "FOR" => {
+                    scopes[current].intro = false
+                }
+                _ => {}
+            },
+            Tok::Table(table) if scopes[current].intro => scopes[current].tables.push(table.clone()),
+            Tok::Alias(alias) => {
+                let scope = &mut scopes[current];
+
+                if let Some(table) = scope.tables.pop() {
+                    scope.renamed.push((table, alias.clone()));
+                }
+
+                scope.tables.push(alias.clone());
+            }
+            Tok::Column(_) | Tok::RuntimeColumn => refs.push((current, tok)),
+            Tok::Opaque => scopes[current].opaque = true,
+            _ => {}
+        }
+
+        prev = Some(tok);
+    }
+
+    (scopes, refs)
+}
+
+/// The scopes visible from `scope`, innermost first
+fn visible(scopes: &[Scope], scope: usize) -> impl Iterator<Item = &Scope> {
+    let mut visible = true;
+    let mut current = Some(scope);
+
+    std::iter::from_fn(move || loop {
+        let scope = &scopes[current?];
+        let was_visible = visible;
+
+        visible = !scope.isolated;
+        current = scope.parent;
+
+        if was_visible {
+            return Some(scope);
+        }
+    })
+}
+
+pub fn check(tokens: &[(Tok, Span)], errors: &mut Vec<Error>) {
+    let (scopes, refs) = self::scopes(tokens);
+
+    for (scope, tok) in refs {
+        let Tok::Column(table) = tok else { continue };
+
+        if table == "Excluded" {
+            continue; // available in ON CONFLICT DO UPDATE
+        }
+
+        let mut visible = true;
+        let mut current = Some(scope);
+        let mut found = false;
+
+        while let Some(idx) = current {
+            let scope = &scopes[idx];
+
+            if scope.opaque || (visible && scope.tables.iter().any(|t| t == table)) {
+                found = true;
+                break;
+            }
+
+            visible = !scope.isolated;
+            current = scope.parent;
+        }
+
+        if found {
+            continue;
+        }
+
+        let mut err = Error::new(
+            table.span(),
+            format!("`{table}` is not in scope here, it must be added to a FROM or JOIN clause of this query"),
+        );
+
+        if let Some((_, alias)) = scopes.iter().flat_map(|s| &s.renamed).find(|(t, _)| t == table) {
+            err.combine(Error::new(
+                alias.span(),
+                format!("`{table}` is renamed to `{alias}` here"),
+            ));
+        } else if let Some(other) = scopes.iter().flat_map(|s| &s.tables).find(|t| *t == table) {
+            err.combine(Error::new(
+                other.span(),
+                format!("`{table}` is only in scope of this query"),
+            ));
+        }
+
+        errors.push(err);
+    }
+}
+
+/// For each `{col!}`, the tables visible to it as `(table, name in the query)` pairs, innermost first.
+/// Renamed tables are listed by their original table and their alias.
+pub fn runtime_columns(tokens: &[(Tok, Span)]) -> Vec<Vec<(Ident, Ident)>> {
+    let (scopes, refs) = self::scopes(tokens);
+
+    refs.into_iter()
+        .filter(|(_, tok)| matches!(tok, Tok::RuntimeColumn))
+        .map(|(scope, _)| {
+            visible(&scopes, scope)
+                .flat_map(|scope| {
+                    scope.tables.iter().map(|name| {
+                        let table = scope.renamed.iter().find(|(_, alias)| alias == name).map_or(name, |(t, _)| t);
+                        (table.clone(), name.clone())
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,25 @@
+//! Spacing rules for canonical SQL, shared with `thorn_macros`.
+//!
+//! `thorn_macros` keeps a copy of this file in `thorn_macros/src/spacing.rs`, which must stay byte-identical,
+//! so that text buffered at compile-time and text written at runtime are spaced identically.
+
+/// Whether a space is needed between two adjacent tokens in canonical SQL.
+///
+/// Tokens are separated by a single space, except before `,` `;` `)` `]`, after `(` `[`,
+/// around `.` and `::`, and before the parentheses of function calls and array subscripts.
+pub(crate) fn needs_space(prev: &str, next: &str) -> bool {
+    let (Some(p), Some(n)) = (prev.chars().next_back(), next.chars().next()) else {
+        return false;
+    };
+
+    if p.is_whitespace() || n.is_whitespace() || prev.ends_with("::") || next.starts_with("::") {
+        return false;
+    }
+
+    match (p, n) {
+        (_, ',' | ';' | ')' | ']') | ('(' | '[', _) | ('.', _) | (_, '.') => false,
+        (_, '(') => !(p.is_ascii_lowercase() || p.is_ascii_digit() || p == '_'),
+        (_, '[') => !(p.is_ascii_alphanumeric() || matches!(p, '_' | '"' | ')' | ']')),
+        _ => true,
+    }
+}